@@ -1,16 +1,40 @@
-use log::{debug, info};
-use std::path::Path;
+use log::{debug, info, warn};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::thread;
 
-use crate::commands::{FtpCommand, get_help_text};
-use crate::config::ClientConfig;
+use crate::commands::{
+    CommandData, CommandOutput, FtpCommand, FxpEndpoint, get_help_text, parse_command,
+};
+use crate::config::{ClientConfig, DataMode, GatewayLogin};
+use crate::connection::data::passive_target;
+use crate::connection::retry::RetryPolicy;
 use crate::connection::{CommandConnection, DataConnection};
 use crate::error::{RaxFtpClientError, Result};
-use crate::responses::{FtpResponse, is_authentication_success, parse_response};
-use crate::terminal::listing::format_directory_listing;
+use crate::netrc::{self, INIT_MACRO, NetrcEntry};
+use crate::responses::{
+    FILE_ACTION_PENDING, FtpResponse, NEED_ACCOUNT, USER_LOGGED_IN, USER_NAME_OK_NEED_PASSWORD,
+    is_authentication_success, parse_pasv_response, parse_pwd_response,
+};
+use crate::terminal::listing::{DirectoryEntry, format_directory_listing};
+use crate::terminal::local;
 use crate::transfer::{
-    download_file_with_progress, read_directory_listing, upload_file_with_progress,
-    validate_download_path, validate_upload_file,
+    download_file_with_progress, format_ftp_timestamp, format_remote_time, fxp_transfer,
+    get_local_mtime, parse_mdtm_response, parse_transfer_size, read_directory_listing,
+    set_local_mtime, upload_file_with_progress, validate_download_path, validate_upload_file,
 };
+use crate::url::DEFAULT_FTP_PORT;
+
+/// Credential requested by the server during login
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginPrompt {
+    /// `331` after USER
+    Password,
+    /// `332` after USER or PASS
+    Account,
+    /// `331` from an FTP gateway without a configured `gateway_password`
+    GatewayPassword,
+}
 
 /// Client connection state
 #[derive(Debug, Clone, PartialEq)]
@@ -35,19 +59,35 @@ pub struct RaxFtpClient {
     connection: CommandConnection,
     state: ClientState,
     config: ClientConfig,
+    /// Settings OPEN starts from when switching servers
+    defaults: ClientConfig,
     data_connection: Option<DataConnection>,
+    features: Option<Vec<String>>,
+    listing_cache: Option<Vec<DirectoryEntry>>,
 }
 
 impl RaxFtpClient {
     /// Create a new FTP client with the given configuration
     pub fn new(config: ClientConfig) -> Self {
+        Self::with_defaults(config.clone(), config)
+    }
+
+    /// Create a client for `config` that switches servers starting from `defaults`
+    ///
+    /// With `--profile`, `config` is the merged profile and `defaults` the
+    /// top-level settings, so OPEN, PROFILES, FXP and LCD never inherit the
+    /// first profile's values.
+    pub fn with_defaults(config: ClientConfig, defaults: ClientConfig) -> Self {
         info!("Creating RAX FTP Client with config: {config}");
 
         Self {
             connection: CommandConnection::new(&config),
             state: ClientState::Disconnected,
+            defaults,
             config,
             data_connection: None,
+            features: None,
+            listing_cache: None,
         }
     }
 
@@ -58,6 +98,329 @@ impl RaxFtpClient {
         Ok(())
     }
 
+    /// Connect to another server or profile, dropping any current session first
+    pub fn open(&mut self, target: &str, port: Option<u16>) -> Result<CommandOutput> {
+        // Resolve the target first so a bad profile leaves the current session alone
+        let config = if port.is_none() && self.defaults.profiles.contains_key(target) {
+            self.defaults
+                .with_profile(target)
+                .map_err(|e| RaxFtpClientError::InvalidConfigValue(e.to_string()))?
+        } else {
+            let mut config = self.defaults.clone();
+            config.host = target.to_string();
+            config.port = port.unwrap_or(DEFAULT_FTP_PORT);
+            config.host_name = None;
+            config.profile_name = None;
+            config
+        };
+
+        if self.is_connected() {
+            self.close()?;
+        }
+
+        info!("Opening connection with config: {config}");
+        self.connection = CommandConnection::new(&config);
+        self.config = config;
+
+        self.reconnect()
+    }
+
+    /// Send QUIT and return to the disconnected state
+    pub fn close(&mut self) -> Result<CommandOutput> {
+        if !self.is_connected() {
+            return Err(RaxFtpClientError::NotConnected("Not connected".to_string()));
+        }
+
+        // The server may already be gone; disconnect regardless
+        let reply = self
+            .send_command("QUIT")
+            .and_then(|()| self.connection.read_response());
+        self.disconnect()?;
+
+        let closed = format!("Disconnected from {}", self.server_name());
+        Ok(match reply {
+            Ok(response) => {
+                let mut output = CommandOutput::from_reply(response);
+                output.text = format!("{}\n{closed}", output.text.trim_end());
+                output
+            }
+            Err(e) => {
+                debug!("No reply to QUIT: {e}");
+                CommandOutput::local(closed)
+            }
+        })
+    }
+
+    /// Close the current connection (if any) and connect to the same server again
+    pub fn reconnect(&mut self) -> Result<CommandOutput> {
+        if self.is_connected() {
+            self.close()?;
+        }
+        self.disconnect()?;
+
+        let greeting = self.connection.connect_with_retries()?;
+        self.state = ClientState::Connected;
+
+        let mut output = CommandOutput::from_reply(greeting);
+        output.text = format!(
+            "Connected to {}{}\n{}",
+            self.server_name(),
+            self.connected_address()
+                .map(|addr| format!(" ({addr})"))
+                .unwrap_or_default(),
+            output.text.trim_end()
+        );
+
+        // A failed netrc login still leaves a usable connection
+        match self.netrc_login() {
+            Ok(Some(login)) => output.text = format!("{}\n{}", output.text, login.text),
+            Ok(None) => {}
+            Err(e) => output.text = format!("{}\nnetrc login failed: {e}", output.text),
+        }
+        Ok(output)
+    }
+
+    /// Log in with the netrc entry for the current host and run its `init` macro
+    ///
+    /// Returns `None` when netrc login is disabled or no entry matches.
+    pub fn netrc_login(&mut self) -> Result<Option<CommandOutput>> {
+        let Some(entry) = netrc::entry_for(&self.config)? else {
+            return Ok(None);
+        };
+        let Some(login) = &entry.login else {
+            return Ok(None);
+        };
+
+        info!("Logging in as '{login}' from netrc");
+        let login = login.clone();
+        let mut output = self.login(&login, |prompt| Ok(netrc_answer(&entry, prompt)))?;
+        let mut lines = vec![output.text.clone()];
+
+        if self.is_authenticated()
+            && let Some(init) = entry.macros.get(INIT_MACRO)
+        {
+            for line in init {
+                lines.push(format!("{INIT_MACRO}> {line}"));
+                match self.execute_command(&parse_command(line)) {
+                    Ok(output) => lines.push(output.text.trim_end().to_string()),
+                    Err(e) => {
+                        lines.push(format!("Command failed: {e}"));
+                        break;
+                    }
+                }
+            }
+        }
+
+        output.text = lines.join("\n");
+        Ok(Some(output))
+    }
+
+    /// Log in as `user` with the password from the netrc entry for the current host
+    ///
+    /// Fails before anything is sent when no entry for `user` has a password,
+    /// rather than leaving the server waiting for PASS.
+    fn login_from_netrc(&mut self, user: &str) -> Result<CommandOutput> {
+        let entry = netrc::entry_for(&self.config)?
+            .filter(|entry| entry.login.as_deref() == Some(user) && entry.password.is_some())
+            .ok_or_else(|| {
+                RaxFtpClientError::NotAuthenticated(format!(
+                    "LOGIN {user} needs a password: add a netrc entry for {} or use USER and PASS",
+                    self.config.login_host()
+                ))
+            })?;
+
+        self.login(user, |prompt| Ok(netrc_answer(&entry, prompt)))
+    }
+
+    /// Log in as `user`, asking `credentials` for the password or account when the server wants one
+    ///
+    /// Handles `230` straight after USER, `331` -> PASS and `332` -> ACCT.
+    /// Returning `None` from `credentials` when the server needs the answer fails the login.
+    /// Behind an FTP gateway the configured `gateway_login` sequence comes first.
+    pub fn login<F>(&mut self, user: &str, mut credentials: F) -> Result<CommandOutput>
+    where
+        F: FnMut(LoginPrompt) -> Result<Option<String>>,
+    {
+        if self.is_authenticated() {
+            return Err(RaxFtpClientError::NotAuthenticated(
+                "Already authenticated. Use LOGOUT first to change user.".to_string(),
+            ));
+        }
+
+        let mut lines = Vec::new();
+        let user = self.gateway_login(user, &mut lines, &mut credentials)?;
+
+        self.send_command(&format!("USER {user}"))?;
+        let mut response = self.read_response()?;
+        lines.push(response.to_string());
+
+        if response.code == USER_NAME_OK_NEED_PASSWORD
+            && let Some(password) = credentials(LoginPrompt::Password)?
+        {
+            self.send_command(&format!("PASS {password}"))?;
+            response = self.read_response()?;
+            lines.push(response.to_string());
+        }
+
+        if response.code == NEED_ACCOUNT
+            && let Some(account) = credentials(LoginPrompt::Account)?
+        {
+            self.send_command(&format!("ACCT {account}"))?;
+            response = self.read_response()?;
+            lines.push(response.to_string());
+        }
+
+        // Stopping at 331/332 would leave the server waiting for PASS or ACCT
+        if matches!(response.code, USER_NAME_OK_NEED_PASSWORD | NEED_ACCOUNT) {
+            return Err(RaxFtpClientError::NotAuthenticated(format!(
+                "Login as '{user}' incomplete, the server still expects credentials: {response}"
+            )));
+        }
+
+        CommandOutput::from_lines(lines, &response).into_result()
+    }
+
+    /// Send USER, preceded by the gateway login sequence when one is configured
+    ///
+    /// There is no one to ask for a gateway password here, so it must be configured.
+    fn handle_user_command(&mut self, user: &str) -> Result<CommandOutput> {
+        let mut lines = Vec::new();
+        let user = self.gateway_login(user, &mut lines, &mut |_| Ok(None))?;
+
+        self.send_command(&format!("USER {user}"))?;
+        let response = self.read_response()?;
+        lines.push(response.to_string());
+        Ok(CommandOutput::from_lines(lines, &response))
+    }
+
+    /// Run the gateway part of the login and return the USER argument for the real server
+    ///
+    /// Logs in to the gateway itself when `gateway_user` is set, asking
+    /// `credentials` for the password unless `gateway_password` is configured,
+    /// then names the target with `USER user@target`, `SITE target` or `OPEN target`.
+    fn gateway_login<F>(
+        &mut self,
+        user: &str,
+        lines: &mut Vec<String>,
+        credentials: &mut F,
+    ) -> Result<String>
+    where
+        F: FnMut(LoginPrompt) -> Result<Option<String>>,
+    {
+        let style = self.config.gateway_login;
+        let Some(target) = self.config.gateway_target.clone() else {
+            return Ok(user.to_string());
+        };
+        if style == GatewayLogin::None {
+            return Ok(user.to_string());
+        }
+        info!("Logging in through the FTP gateway to {target} ({style:?})");
+
+        if let Some(gateway_user) = self.config.gateway_user.clone() {
+            self.send_command(&format!("USER {gateway_user}"))?;
+            let mut response = self.read_response()?;
+            lines.push(response.to_string());
+
+            if response.code == USER_NAME_OK_NEED_PASSWORD {
+                let password = match self.config.gateway_password.clone() {
+                    Some(password) => password,
+                    None => credentials(LoginPrompt::GatewayPassword)?.ok_or_else(|| {
+                        RaxFtpClientError::NotAuthenticated(format!(
+                            "The gateway wants a password for '{gateway_user}': set gateway_password to log in without a prompt"
+                        ))
+                    })?,
+                };
+                self.send_command(&format!("PASS {password}"))?;
+                response = self.read_response()?;
+                lines.push(response.to_string());
+            }
+            if !response.is_positive_completion() {
+                return Err(unexpected_reply_error("gateway login", &response));
+            }
+            // A 230 from the gateway is not a login to the real server
+            if response.code == USER_LOGGED_IN {
+                self.state = ClientState::Connected;
+            }
+        }
+
+        let command = match style {
+            GatewayLogin::None | GatewayLogin::UserAtHost => None,
+            GatewayLogin::Site => Some(format!("SITE {target}")),
+            GatewayLogin::Open => Some(format!("OPEN {target}")),
+        };
+        if let Some(command) = command {
+            self.send_command(&command)?;
+            let response = self.read_response()?;
+            lines.push(response.to_string());
+            if !response.is_positive_completion() {
+                return Err(unexpected_reply_error(&command, &response));
+            }
+        }
+
+        Ok(match style {
+            GatewayLogin::UserAtHost => format!("{user}@{target}"),
+            _ => user.to_string(),
+        })
+    }
+
+    /// List configured profiles, marking the one in use
+    fn profiles_text(&self) -> String {
+        if self.defaults.profiles.is_empty() {
+            return "No profiles configured. Add [profiles.<name>] tables to config.toml."
+                .to_string();
+        }
+
+        let mut lines = vec!["Profiles:".to_string()];
+        for name in self.defaults.profiles.keys() {
+            let Some(profile) = self.defaults.profile(name) else {
+                continue;
+            };
+            let marker = if self.config.profile_name.as_deref() == Some(name) {
+                '*'
+            } else {
+                ' '
+            };
+            lines.push(format!(
+                "{marker} {name:<16} {}:{} ({})",
+                profile.host, profile.port, profile.data_mode
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Entries of the current remote directory from the last LIST, while still valid
+    ///
+    /// Used for tab completion, which never lists by itself: that would run a
+    /// data transfer and print replies in the middle of the line being edited.
+    pub fn cached_entries(&self) -> &[DirectoryEntry] {
+        self.listing_cache.as_deref().unwrap_or_default()
+    }
+
+    /// Local directory used for transfers
+    pub fn local_directory(&self) -> &str {
+        &self.config.local_directory
+    }
+
+    /// Name of the current server, as shown in the prompt and HELP
+    pub fn server_name(&self) -> String {
+        self.config.display_name()
+    }
+
+    /// Address to connect to for a PASV reply, after `passive_address` rewriting
+    pub fn passive_target(&self, reported: SocketAddr) -> Result<SocketAddr> {
+        let control = self.connection.addresses()?;
+        Ok(passive_target(
+            reported,
+            control.peer_ip,
+            self.config.passive_address,
+        ))
+    }
+
+    /// Server address of the current connection
+    pub fn connected_address(&self) -> Option<SocketAddr> {
+        self.connection.peer_addr()
+    }
+
     /// Get current client state for display
     pub fn get_state(&self) -> &ClientState {
         &self.state
@@ -78,42 +441,57 @@ impl RaxFtpClient {
         self.connection.disconnect()?;
         self.state = ClientState::Disconnected;
         self.data_connection = None; // Clear data connection
+        self.features = None;
+        self.listing_cache = None;
         Ok(())
     }
 
     /// Send a command and handle file transfers if needed
-    pub fn execute_command(&mut self, command: &FtpCommand) -> Result<String> {
-        // Handle client-side only commands
-        if command.is_client_only() {
-            // For HELP command, get formatted help text
-            if let FtpCommand::Help = command {
-                let mut help_text = get_help_text();
-                help_text = help_text.replace("[SERVER_PLACEHOLDER]", &self.config.host);
-                help_text = help_text.replace("[STATE_PLACEHOLDER]", &self.state.to_string());
-                help_text =
-                    help_text.replace("[LOCAL_DIR_PLACEHOLDER]", &self.config.local_directory);
-                let (start, end) = self.config.get_data_port_range();
-                help_text =
-                    help_text.replace("[PORT_RANGE_PLACEHOLDER]", &format!("{start}-{end}"));
-
-                return Ok(help_text);
+    pub fn execute_command(&mut self, command: &FtpCommand) -> Result<CommandOutput> {
+        // Connection management works in any state
+        match command {
+            FtpCommand::Open(host, port) => return self.open(host, *port),
+            FtpCommand::Close => return self.close(),
+            FtpCommand::Reconnect => return self.reconnect(),
+            FtpCommand::Quit if self.is_connected() => return self.close(),
+            FtpCommand::Quit => return Ok(CommandOutput::local("Not connected".to_string())),
+            // FXP runs in sessions of its own, one per profile
+            FtpCommand::Fxp(source, destination) => {
+                return self.handle_fxp_command(source, destination);
+            }
+            // Without a terminal to prompt on, the password has to come from netrc
+            FtpCommand::Login(Some(user)) => return self.login_from_netrc(user),
+            FtpCommand::Login(None) => {
+                return Err(RaxFtpClientError::NotAuthenticated(
+                    "LOGIN needs a username when it cannot prompt".to_string(),
+                ));
+            }
+            // Unrecognised input is reported locally, never sent to the server
+            FtpCommand::Unknown(message) => {
+                return Err(RaxFtpClientError::InvalidCommand(message.clone()));
             }
+            _ => {}
+        }
 
-            return Ok("Command executed locally".to_string());
+        // Handle client-side only commands
+        if command.is_client_only() {
+            return self.execute_local_command(command);
         }
 
         // Check authentication for commands that require it
-        // Commands like USER, PASS, QUIT, and UNKNOWN don't require authentication
+        // Commands like USER, PASS and QUIT don't require authentication; QUOTE
+        // and SITE are left for the server to judge
         let requires_auth = !matches!(
             command,
-            FtpCommand::User(_) | FtpCommand::Pass(_) | FtpCommand::Quit | FtpCommand::Unknown(_)
+            FtpCommand::User(_)
+                | FtpCommand::Pass(_)
+                | FtpCommand::Quit
+                | FtpCommand::Quote(_)
+                | FtpCommand::Site(_)
         );
 
         if requires_auth && !self.is_authenticated() {
-            return Err(RaxFtpClientError::NotAuthenticated(format!(
-                "{} Not authenticated. Please log in first.",
-                crate::responses::status_codes::CLIENT_ERROR_NOT_AUTHENTICATED
-            )));
+            return Err(not_authenticated_error());
         }
 
         // Check if trying to authenticate when already authenticated
@@ -123,43 +501,218 @@ impl RaxFtpClient {
             ));
         }
 
-        // Execute the command
-        match command {
+        // Commands that change the remote directory contents invalidate the cached listing
+        if matches!(
+            command,
+            FtpCommand::Stor(_)
+                | FtpCommand::Del(_)
+                | FtpCommand::Mkd(_)
+                | FtpCommand::Rmd(_)
+                | FtpCommand::Cwd(_)
+                | FtpCommand::Rename(_, _)
+        ) {
+            self.listing_cache = None;
+        }
+
+        // Execute the command; a negative final reply becomes a typed error
+        let output = match command {
             FtpCommand::Stor(filename) => self.handle_stor_command(filename),
             FtpCommand::Retr(filename) => self.handle_retr_command(filename),
             FtpCommand::List => self.handle_list_command(),
-            FtpCommand::Port(addr) => self.handle_port_command(addr),
-            FtpCommand::Pasv => self.handle_pasv_command(),
+            FtpCommand::Port(addr) => self
+                .handle_port_command(addr)
+                .map(CommandOutput::from_reply),
+            FtpCommand::Pasv => self.handle_pasv_command().map(CommandOutput::from_reply),
+            FtpCommand::Mdtm(filename) => self.handle_mdtm_command(filename),
+            FtpCommand::Quote(line) => self.handle_quote_command(line),
+            FtpCommand::Rename(from, to) => self.rename(from, to),
+            FtpCommand::User(user) => self.handle_user_command(user),
+            FtpCommand::Pwd => {
+                self.send_command("PWD")?;
+                let response = self.read_response()?;
+                let output = match parse_pwd_response(&response.to_string()) {
+                    Some(path) => CommandOutput::from_reply(response)
+                        .with_data(CommandData::WorkingDirectory { path }),
+                    None => CommandOutput::from_reply(response),
+                };
+                Ok(output)
+            }
             // For all other commands, send normally
             _ => {
                 let command_str = command.to_ftp_string();
                 self.send_command(&command_str)?;
-                self.read_response()
+                self.read_response().map(CommandOutput::from_reply)
+            }
+        }?;
+        output.into_result()
+    }
+
+    /// Copy a file between two profiles' servers with FXP
+    fn handle_fxp_command(
+        &self,
+        source: &FxpEndpoint,
+        destination: &FxpEndpoint,
+    ) -> Result<CommandOutput> {
+        let mut source_session = self.profile_session(&source.profile)?;
+        let mut destination_session = self.profile_session(&destination.profile)?;
+
+        let result = fxp_transfer(
+            &mut source_session,
+            &source.path,
+            &mut destination_session,
+            &destination.path,
+        );
+
+        for session in [&mut source_session, &mut destination_session] {
+            if let Err(e) = session.close() {
+                debug!("Closing FXP session: {e}");
+            }
+        }
+        result
+    }
+
+    /// A new session for a profile, logged in from netrc
+    fn profile_session(&self, name: &str) -> Result<RaxFtpClient> {
+        if !self.defaults.profiles.contains_key(name) {
+            return Err(RaxFtpClientError::InvalidConfigValue(format!(
+                "Unknown profile '{name}'"
+            )));
+        }
+        let config = self
+            .defaults
+            .with_profile(name)
+            .map_err(|e| RaxFtpClientError::InvalidConfigValue(e.to_string()))?;
+
+        let mut session = RaxFtpClient::new(config);
+        session.reconnect()?;
+        if !session.is_authenticated() {
+            return Err(RaxFtpClientError::NotAuthenticated(format!(
+                "Profile '{name}' could not log in: FXP needs a netrc entry for {}",
+                session.config.login_host()
+            )));
+        }
+        Ok(session)
+    }
+
+    /// Execute a command that never reaches the server
+    fn execute_local_command(&mut self, command: &FtpCommand) -> Result<CommandOutput> {
+        let local_directory = PathBuf::from(&self.config.local_directory);
+
+        let text = match command {
+            // For HELP command, get formatted help text
+            FtpCommand::Help => {
+                let mut help_text = get_help_text();
+                help_text = help_text.replace("[SERVER_PLACEHOLDER]", &self.server_name());
+                help_text = help_text.replace("[STATE_PLACEHOLDER]", &self.state.to_string());
+                help_text =
+                    help_text.replace("[LOCAL_DIR_PLACEHOLDER]", &self.config.local_directory);
+                let (start, end) = self.config.get_data_port_range();
+                help_text.replace("[PORT_RANGE_PLACEHOLDER]", &format!("{start}-{end}"))
+            }
+            FtpCommand::Profiles => self.profiles_text(),
+            FtpCommand::Lcd(path) => {
+                let target = match path {
+                    Some(path) => local::resolve_path(&local_directory, path),
+                    None => PathBuf::from(&self.defaults.local_directory),
+                };
+                let directory = local::change_directory(&target)?;
+                self.config.local_directory = directory.display().to_string();
+                format!("Local directory now {}", self.config.local_directory)
+            }
+            FtpCommand::Lpwd => format!("Local directory: {}", self.config.local_directory),
+            FtpCommand::Lls(path) => {
+                let path = path.as_deref().unwrap_or(".");
+                local::list_directory(&local::resolve_path(&local_directory, path))?
+            }
+            FtpCommand::Lmkdir(path) => {
+                local::make_directory(&local::resolve_path(&local_directory, path))?
+            }
+            FtpCommand::Shell(shell_command) => local::run_shell(shell_command, &local_directory)?,
+            _ => "Command executed locally".to_string(),
+        };
+
+        Ok(CommandOutput::local(text))
+    }
+
+    /// Rename or move a remote file with RNFR/RNTO
+    ///
+    /// The rename is done by the server in one step, so uploading to a
+    /// temporary name and renaming it into place never exposes a partial file.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<CommandOutput> {
+        if !self.is_authenticated() {
+            return Err(not_authenticated_error());
+        }
+        self.listing_cache = None;
+
+        self.send_command(&format!("RNFR {from}"))?;
+        let rnfr_response = self.read_response()?;
+        if rnfr_response.code != FILE_ACTION_PENDING {
+            return Err(unexpected_reply_error("RNFR", &rnfr_response));
+        }
+
+        self.send_command(&format!("RNTO {to}"))?;
+        let rnto_response = self.read_response()?;
+        if !rnto_response.is_positive_completion() {
+            return Err(unexpected_reply_error("RNTO", &rnto_response));
+        }
+
+        Ok(CommandOutput::from_lines(
+            vec![rnfr_response.to_string(), rnto_response.to_string()],
+            &rnto_response,
+        ))
+    }
+
+    /// Send a raw line and return every reply up to the final one
+    fn handle_quote_command(&mut self, line: &str) -> Result<CommandOutput> {
+        // Anything may have changed behind the client's back
+        self.listing_cache = None;
+        self.features = None;
+
+        self.send_command(line)?;
+        let mut replies = Vec::new();
+        loop {
+            let response = self.read_response()?;
+            let preliminary = response.is_positive_preliminary();
+            replies.push(response.to_string());
+            if !preliminary {
+                return Ok(CommandOutput::from_lines(replies, &response));
             }
         }
     }
 
     /// Handle PORT command - switch to active mode
-    fn handle_port_command(&mut self, addr: &str) -> Result<String> {
+    fn handle_port_command(&mut self, addr: &str) -> Result<FtpResponse> {
         // Parse the address to validate format
-        let parsed_addr: std::net::SocketAddr = addr.parse().map_err(|_| {
+        let parsed_addr: SocketAddr = addr.parse().map_err(|_| {
             RaxFtpClientError::InvalidConfigValue("Invalid address format. Use IP:PORT".to_string())
         })?;
 
         // Validate port is within configured range
         let (start, end) = self.config.get_data_port_range();
         DataConnection::validate_port_range(parsed_addr.port(), start, end)?;
+        self.refuse_active_through_proxy()?;
 
         // Create the data connection with listener on the SPECIFIC port from PORT command
-        let data_connection = DataConnection::active_mode(parsed_addr.port())?;
+        let control = self.connection.addresses()?;
+        let data_connection =
+            DataConnection::active_mode(parsed_addr.port(), control, &self.config)?;
+
+        self.send_port_command(parsed_addr, data_connection)
+    }
 
+    /// Send PORT for a listener that is already bound and keep it on success
+    fn send_port_command(
+        &mut self,
+        parsed_addr: SocketAddr,
+        data_connection: DataConnection,
+    ) -> Result<FtpResponse> {
         // Send PORT command to server
-        let command_str = format!("PORT {addr}");
+        let command_str = format!("PORT {parsed_addr}");
         self.send_command(&command_str)?;
         let response = self.read_response()?;
 
         // If successful, store both connection info and the actual connection
-        if response.starts_with("200") {
+        if response.code == 200 {
             info!(
                 "Switching to active mode, storing data connection with listener on port {}",
                 parsed_addr.port()
@@ -173,37 +726,103 @@ impl RaxFtpClient {
 
         Ok(response)
     }
-    fn handle_pasv_command(&mut self) -> Result<String> {
+
+    /// Switch to active mode on the first free port of the data port range
+    fn auto_active_mode(&mut self) -> Result<FtpResponse> {
+        self.refuse_active_through_proxy()?;
+        let control = self.connection.addresses()?;
+        let (start, end) = self.config.get_data_port_range();
+
+        for port in start..=end {
+            match DataConnection::active_mode(port, control, &self.config) {
+                Ok(data_connection) => {
+                    let advertised = data_connection
+                        .advertised_address()
+                        .unwrap_or(SocketAddr::new(control.local_ip, port));
+                    return self.send_port_command(advertised, data_connection);
+                }
+                Err(e) => debug!("Data port {port} unavailable: {e}"),
+            }
+        }
+
+        Err(RaxFtpClientError::DataConnectionFailed(format!(
+            "No free port in data port range {start}-{end}"
+        )))
+    }
+
+    /// Active mode needs the server to reach us directly, which a proxy rules out
+    fn refuse_active_through_proxy(&self) -> Result<()> {
+        if self.config.proxy.is_enabled() {
+            return Err(RaxFtpClientError::InvalidConfigValue(format!(
+                "active mode cannot be used through a {} proxy, use passive mode (PASV or --passive)",
+                self.config.proxy.kind
+            )));
+        }
+        Ok(())
+    }
+
+    /// Set up a data connection in the configured mode if none is set yet
+    ///
+    /// Returns the PASV/PORT reply when a new mode was negotiated.
+    fn ensure_data_connection(&mut self) -> Result<Option<FtpResponse>> {
+        if self.data_connection.is_some() {
+            return Ok(None);
+        }
+
+        let response = match self.config.data_mode {
+            DataMode::Passive => self.handle_pasv_command()?,
+            DataMode::Active => self.auto_active_mode()?,
+        };
+
+        if self.data_connection.is_none() {
+            return Err(RaxFtpClientError::DataConnectionFailed(format!(
+                "Could not set up {} data connection: {}",
+                self.config.data_mode, response
+            )));
+        }
+
+        Ok(Some(response))
+    }
+
+    fn handle_pasv_command(&mut self) -> Result<FtpResponse> {
         self.send_command("PASV")?;
         let response = self.read_response()?;
 
-        if response.starts_with("227") {
+        if response.code == 227 {
             // Parse server's response to get host:port
-            if let Some(start) = response.find('(') {
-                if let Some(end) = response.find(')') {
-                    let addr_str = &response[start + 1..end];
-                    if let Ok(addr) = addr_str.parse::<std::net::SocketAddr>() {
-                        // Create DataConnection immediately (like PORT does)
-                        let data_connection =
-                            DataConnection::passive_mode(&addr.ip().to_string(), addr.port())?;
-
-                        // Store the actual connection
-                        self.data_connection = Some(data_connection);
-                    }
-                }
+            if let Some(addr) = parse_pasv_response(&response.message) {
+                // Create DataConnection immediately (like PORT does)
+                let control = self.connection.addresses()?;
+                let data_connection = DataConnection::passive_mode(addr, control, &self.config)?;
+
+                // Store the actual connection
+                self.data_connection = Some(data_connection);
             }
         }
 
         Ok(response)
     }
 
-    fn handle_list_command(&mut self) -> Result<String> {
+    /// Open the data connection for a transfer the server has accepted
+    fn connect_data_connection(&mut self, data_connection: &mut DataConnection) -> Result<()> {
+        if let Err(e) = data_connection.connect_to_server() {
+            // The server replies (usually 425) once it gives up on the data
+            // connection; consume that reply so the next command gets its own
+            match self.read_response() {
+                Ok(response) => debug!("Reply after failed data connection: {response}"),
+                Err(read_error) => debug!("No reply after failed data connection: {read_error}"),
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn handle_list_command(&mut self) -> Result<CommandOutput> {
         let mut responses = Vec::new();
 
-        // Auto-PASV if no data connection mode is set
-        if self.data_connection.is_none() {
-            let pasv_response = self.handle_pasv_command()?;
-            responses.push(pasv_response);
+        // Set up the configured data connection mode if none is set yet
+        if let Some(setup_response) = self.ensure_data_connection()? {
+            responses.push(setup_response.to_string());
         }
 
         info!("Data connection present");
@@ -214,19 +833,18 @@ impl RaxFtpClient {
         // 2. Read "150 Opening data connection" response
         let list_response = self.read_response()?;
 
-        // Check if LIST command was accepted (should be "150", not "226")
-        if !list_response.starts_with("150") {
-            responses.push(format!("LIST command failed: {list_response}"));
-            return Ok(responses.join("\n"));
+        // Check if LIST command was accepted (a 1xx reply, not "226")
+        if !list_response.is_positive_preliminary() {
+            return Err(unexpected_reply_error("LIST", &list_response));
         }
 
         // Add the "150" response
-        responses.push(list_response);
+        responses.push(list_response.to_string());
 
         let mut data_connection = self.data_connection.take().unwrap();
 
         // 3. NOW establish data connection
-        data_connection.connect_to_server()?;
+        self.connect_data_connection(&mut data_connection)?;
 
         // 4. Read directory listing from data channel
         match read_directory_listing(&mut data_connection) {
@@ -235,6 +853,13 @@ impl RaxFtpClient {
                 let listing_display = format_directory_listing(&listing);
                 responses.push(listing_display);
 
+                // Remember the entries so later transfers can look up file sizes
+                let entries: Vec<DirectoryEntry> = listing
+                    .iter()
+                    .map(|raw| DirectoryEntry::from_raw(raw))
+                    .collect();
+                self.listing_cache = Some(entries.clone());
+
                 // Reset connection for next use
                 data_connection.reset_connection()?;
 
@@ -243,13 +868,17 @@ impl RaxFtpClient {
 
                 // 5. Read final "226 Directory send OK" response
                 let final_response = self.read_response()?;
-                responses.push(final_response);
+                responses.push(final_response.to_string());
 
-                Ok(responses.join("\n"))
+                CommandOutput::from_lines(responses, &final_response)
+                    .with_data(CommandData::Listing { entries })
+                    .into_result()
             }
             Err(e) => {
-                // Reset connection even on error
-                data_connection.reset_connection()?;
+                // Reset connection even on error, keeping the transfer error
+                if let Err(reset_error) = data_connection.reset_connection() {
+                    debug!("Could not close data connection: {reset_error}");
+                }
 
                 // Put the data connection back
                 self.data_connection = Some(data_connection);
@@ -261,19 +890,52 @@ impl RaxFtpClient {
         }
     }
 
-    fn handle_stor_command(&mut self, filename: &str) -> Result<String> {
-        let mut responses = Vec::new();
-
+    fn handle_stor_command(&mut self, filename: &str) -> Result<CommandOutput> {
         // Build local file path using the config's local directory
         let local_path = Path::new(&self.config.local_directory).join(filename);
 
+        self.store_file(&local_path, filename)
+    }
+
+    /// Upload a local file to the given remote name
+    pub fn store_file(&mut self, local_path: &Path, filename: &str) -> Result<CommandOutput> {
+        if !self.is_authenticated() {
+            return Err(not_authenticated_error());
+        }
+        self.listing_cache = None;
+
         // Basic validation
-        validate_upload_file(&local_path)?;
+        validate_upload_file(local_path)?;
+
+        self.with_transfer_retries("STOR", filename, |client, retry| {
+            let offset = if retry {
+                let local_size = std::fs::metadata(local_path)?.len();
+                let remote_size = client.remote_file_size(filename).unwrap_or(0);
+                client.resume_offset(remote_size.min(local_size))?
+            } else {
+                0
+            };
+            client.store_file_from(local_path, filename, offset)
+        })
+    }
 
-        // Auto-PASV if no data connection mode is set
-        if self.data_connection.is_none() {
-            let pasv_response = self.handle_pasv_command()?;
-            responses.push(pasv_response);
+    /// One STOR attempt, starting `offset` bytes into the local file
+    fn store_file_from(
+        &mut self,
+        local_path: &Path,
+        filename: &str,
+        offset: u64,
+    ) -> Result<CommandOutput> {
+        let mut responses = Vec::new();
+
+        // Set up the configured data connection mode if none is set yet
+        if let Some(setup_response) = self.ensure_data_connection()? {
+            responses.push(setup_response.to_string());
+        }
+
+        // Ask the server to continue after the bytes it already has
+        if offset > 0 {
+            responses.push(self.send_rest(offset)?.to_string());
         }
 
         info!("Data connection present");
@@ -285,23 +947,22 @@ impl RaxFtpClient {
         // 2. Read "150 Opening data connection" response
         let stor_response = self.read_response()?;
 
-        // Check if STOR command was accepted (should be "150", not "226")
-        if !stor_response.starts_with("150") {
-            responses.push(format!("STOR command failed: {stor_response}"));
-            return Ok(responses.join("\n"));
+        // Check if STOR command was accepted (a 1xx reply, not "226")
+        if !stor_response.is_positive_preliminary() {
+            return Err(unexpected_reply_error("STOR", &stor_response));
         }
 
         // Add the "150" response
-        responses.push(stor_response);
+        responses.push(stor_response.to_string());
 
         let mut data_connection = self.data_connection.take().unwrap();
 
         // 3. NOW establish data connection
-        data_connection.connect_to_server()?;
+        self.connect_data_connection(&mut data_connection)?;
 
         // 4. Upload the file with progress
-        match upload_file_with_progress(&mut data_connection, &local_path, filename) {
-            Ok(()) => {
+        match upload_file_with_progress(&mut data_connection, local_path, filename, offset) {
+            Ok(summary) => {
                 // Reset connection for next use
                 data_connection.reset_connection()?;
 
@@ -310,12 +971,31 @@ impl RaxFtpClient {
 
                 // 5. Read final response from server
                 let final_response = self.read_response()?;
-                responses.push(final_response);
-                Ok(responses.join("\n"))
+                let completed = final_response.is_positive_completion();
+                responses.push(final_response.to_string());
+
+                // 6. Ask the server to keep the local modification time
+                if completed && self.config.preserve_upload_mtime {
+                    match self.push_local_mtime(filename, local_path) {
+                        Ok(Some(mfmt_response)) => responses.push(mfmt_response.to_string()),
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!("Could not preserve modification time of '{filename}': {e}");
+                            responses
+                                .push(format!("Warning: modification time not preserved: {e}"));
+                        }
+                    }
+                }
+
+                CommandOutput::from_lines(responses, &final_response)
+                    .with_data(CommandData::Transfer(summary))
+                    .into_result()
             }
             Err(e) => {
-                // Reset connection even on error
-                data_connection.reset_connection()?;
+                // Reset connection even on error, keeping the transfer error
+                if let Err(reset_error) = data_connection.reset_connection() {
+                    debug!("Could not close data connection: {reset_error}");
+                }
 
                 // Put the data connection back
                 self.data_connection = Some(data_connection);
@@ -328,19 +1008,53 @@ impl RaxFtpClient {
     }
 
     /// Handle RETR command with connection timing fix
-    fn handle_retr_command(&mut self, filename: &str) -> Result<String> {
-        let mut responses = Vec::new();
-
+    fn handle_retr_command(&mut self, filename: &str) -> Result<CommandOutput> {
         // Build local file path using the config's local directory
         let local_path = Path::new(&self.config.local_directory).join(filename);
 
+        self.retrieve_file(filename, &local_path)
+    }
+
+    /// Download a remote file to the given local path
+    pub fn retrieve_file(&mut self, filename: &str, local_path: &Path) -> Result<CommandOutput> {
+        if !self.is_authenticated() {
+            return Err(not_authenticated_error());
+        }
+
         // Basic validation (check if file already exists, directory is writable, etc.)
-        validate_download_path(&local_path)?;
+        validate_download_path(local_path)?;
+
+        self.with_transfer_retries("RETR", filename, |client, retry| {
+            let offset = match std::fs::metadata(local_path) {
+                Ok(metadata) if retry => client.resume_offset(metadata.len())?,
+                _ => 0,
+            };
+            client.retrieve_file_from(filename, local_path, offset)
+        })
+    }
 
-        // Auto-PASV if no data connection mode is set
-        if self.data_connection.is_none() {
-            let pasv_response = self.handle_pasv_command()?;
-            responses.push(pasv_response);
+    /// One RETR attempt, appending to the local file from `offset` on
+    fn retrieve_file_from(
+        &mut self,
+        filename: &str,
+        local_path: &Path,
+        offset: u64,
+    ) -> Result<CommandOutput> {
+        let mut responses = Vec::new();
+
+        // Learn the file size up front so progress can show a real percentage
+        let mut expected_size = self
+            .remote_file_size(filename)
+            .map(|size| size.saturating_sub(offset));
+
+        // Set up the configured data connection mode if none is set yet
+        if let Some(setup_response) = self.ensure_data_connection()? {
+            responses.push(setup_response.to_string());
+        }
+
+        // Ask the server to skip the bytes already downloaded
+        if offset > 0 {
+            responses.push(self.send_rest(offset)?.to_string());
         }
 
         info!("Data connection present");
@@ -352,23 +1066,33 @@ impl RaxFtpClient {
         // 2. Read "150 Opening data connection" response
         let retr_response = self.read_response()?;
 
-        // Check if RETR command was accepted (should be "150", not "226")
-        if !retr_response.starts_with("150") {
-            responses.push(format!("RETR command failed: {retr_response}"));
-            return Ok(responses.join("\n"));
+        // Check if RETR command was accepted (a 1xx reply, not "226")
+        if !retr_response.is_positive_preliminary() {
+            return Err(unexpected_reply_error("RETR", &retr_response));
+        }
+
+        // Fall back to the byte count announced in the "150" reply
+        if expected_size.is_none() {
+            expected_size = parse_transfer_size(&retr_response.message);
         }
 
         // Add the "150" response
-        responses.push(retr_response);
+        responses.push(retr_response.to_string());
 
         let mut data_connection = self.data_connection.take().unwrap();
 
         // 3. NOW establish data connection
-        data_connection.connect_to_server()?;
+        self.connect_data_connection(&mut data_connection)?;
 
         // 4. Download the file with progress
-        match download_file_with_progress(&mut data_connection, &local_path, filename) {
-            Ok(()) => {
+        match download_file_with_progress(
+            &mut data_connection,
+            local_path,
+            filename,
+            expected_size,
+            offset,
+        ) {
+            Ok(summary) => {
                 // Reset connection for next use
                 data_connection.reset_connection()?;
 
@@ -377,12 +1101,27 @@ impl RaxFtpClient {
 
                 // 5. Read final response from server
                 let final_response = self.read_response()?;
-                responses.push(final_response);
-                Ok(responses.join("\n"))
+                let completed = final_response.is_positive_completion();
+                responses.push(final_response.to_string());
+
+                // 6. Carry the remote modification time over to the local file
+                if completed
+                    && self.config.preserve_download_mtime
+                    && let Err(e) = self.apply_remote_mtime(filename, local_path)
+                {
+                    warn!("Could not preserve modification time of '{filename}': {e}");
+                    responses.push(format!("Warning: modification time not preserved: {e}"));
+                }
+
+                CommandOutput::from_lines(responses, &final_response)
+                    .with_data(CommandData::Transfer(summary))
+                    .into_result()
             }
             Err(e) => {
-                // Reset connection even on error
-                data_connection.reset_connection()?;
+                // Reset connection even on error, keeping the transfer error
+                if let Err(reset_error) = data_connection.reset_connection() {
+                    debug!("Could not close data connection: {reset_error}");
+                }
 
                 // Put the data connection back
                 self.data_connection = Some(data_connection);
@@ -394,27 +1133,164 @@ impl RaxFtpClient {
         }
     }
 
+    /// Run a transfer, retrying transient failures when `[retry] transfers` is set
+    ///
+    /// `transfer` is told whether this is a retry, so it can resume with REST.
+    fn with_transfer_retries<F>(
+        &mut self,
+        command: &str,
+        filename: &str,
+        mut transfer: F,
+    ) -> Result<CommandOutput>
+    where
+        F: FnMut(&mut Self, bool) -> Result<CommandOutput>,
+    {
+        let policy = RetryPolicy::new(&self.config);
+        let mut attempt = 1;
+
+        loop {
+            match transfer(self, attempt > 1) {
+                Err(e)
+                    if self.config.retry.transfers
+                        && e.is_transient()
+                        && policy.can_retry(attempt)
+                        && self.is_connected() =>
+                {
+                    let delay = policy.delay(attempt);
+                    warn!(
+                        "{command} {filename} failed on attempt {attempt}/{}: {e}; retrying in {delay:.1?}",
+                        policy.max_attempts
+                    );
+                    // Negotiate a fresh data connection for the next attempt
+                    self.data_connection = None;
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Offset to resume a transfer at: `transferred` if the server supports REST
+    fn resume_offset(&mut self, transferred: u64) -> Result<u64> {
+        if transferred == 0 || !self.server_supports("REST")? {
+            return Ok(0);
+        }
+        Ok(transferred)
+    }
+
+    /// Send REST so the next transfer command starts at `offset`
+    fn send_rest(&mut self, offset: u64) -> Result<FtpResponse> {
+        self.send_command(&format!("REST {offset}"))?;
+        let response = self.read_response()?;
+        if response.code != FILE_ACTION_PENDING {
+            return Err(unexpected_reply_error("REST", &response));
+        }
+        Ok(response)
+    }
+
+    /// Handle MDTM command - show the remote modification time in local time and UTC
+    fn handle_mdtm_command(&mut self, filename: &str) -> Result<CommandOutput> {
+        self.send_command(&format!("MDTM {filename}"))?;
+        let response = self.read_response()?;
+
+        if response.code != 213 {
+            return Ok(CommandOutput::from_reply(response));
+        }
+
+        let remote_time = parse_mdtm_response(&response.to_string())?;
+        let mut output = CommandOutput::from_reply(response);
+        output.text = format!("{filename}:\n{}", format_remote_time(&remote_time));
+        Ok(output.with_data(CommandData::ModificationTime {
+            file: filename.to_string(),
+            time: remote_time,
+        }))
+    }
+
+    /// Look up the size of a remote file with SIZE, falling back to the cached listing
+    fn remote_file_size(&mut self, filename: &str) -> Option<u64> {
+        let size_reply = self
+            .send_command(&format!("SIZE {filename}"))
+            .and_then(|()| self.read_response());
+
+        match size_reply {
+            Ok(response) if response.code == 213 => {
+                if let Ok(size) = response.message.trim().parse() {
+                    return Some(size);
+                }
+                debug!("Could not parse SIZE reply: {response}");
+            }
+            Ok(response) => debug!("SIZE not available: {response}"),
+            Err(e) => debug!("SIZE command failed: {e}"),
+        }
+
+        self.listing_cache
+            .as_ref()?
+            .iter()
+            .find(|entry| entry.name == filename)
+            .and_then(|entry| entry.size)
+    }
+
+    /// Fetch the remote modification time with MDTM and apply it to a local file
+    fn apply_remote_mtime(&mut self, filename: &str, local_path: &Path) -> Result<()> {
+        self.send_command(&format!("MDTM {filename}"))?;
+        let response = self.read_response()?;
+        let remote_time = parse_mdtm_response(&response.to_string())?;
+
+        set_local_mtime(local_path, &remote_time)
+    }
+
+    /// Send MFMT with the local modification time, if the server advertises it
+    fn push_local_mtime(
+        &mut self,
+        filename: &str,
+        local_path: &Path,
+    ) -> Result<Option<FtpResponse>> {
+        if !self.server_supports("MFMT")? {
+            debug!("Server does not advertise MFMT, skipping modification time update");
+            return Ok(None);
+        }
+
+        let local_time = get_local_mtime(local_path)?;
+        let command = format!("MFMT {} {filename}", format_ftp_timestamp(&local_time));
+        self.send_command(&command)?;
+        let response = self.read_response()?;
+
+        if response.code != 213 {
+            return Err(RaxFtpClientError::InvalidResponse(format!(
+                "MFMT rejected: {response}"
+            )));
+        }
+
+        Ok(Some(response))
+    }
+
+    /// Check whether the server advertises a feature in its FEAT reply
+    fn server_supports(&mut self, feature: &str) -> Result<bool> {
+        if self.features.is_none() {
+            self.send_command("FEAT")?;
+            let response = self.read_response()?;
+            let features = parse_feat_response(&response);
+            debug!("Server features: {features:?}");
+            self.features = Some(features);
+        }
+
+        Ok(self
+            .features
+            .as_ref()
+            .is_some_and(|features| features.iter().any(|f| f.eq_ignore_ascii_case(feature))))
+    }
+
     /// Send a raw FTP command to the server
     pub fn send_command(&mut self, command: &str) -> Result<()> {
         self.connection.send_command(command)
     }
 
     /// Read a response from the server and update state if needed
-    pub fn read_response(&mut self) -> Result<String> {
-        let response_str = self.connection.read_response()?;
-
-        // Parse the response to potentially update client state
-        match parse_response(&response_str) {
-            Ok(parsed_response) => {
-                self.update_state_from_response(&parsed_response);
-                Ok(response_str)
-            }
-            Err(parse_error) => {
-                debug!("Failed to parse response '{response_str}': {parse_error}");
-                // Return the original response even if parsing failed
-                Ok(response_str)
-            }
-        }
+    pub fn read_response(&mut self) -> Result<FtpResponse> {
+        let response = self.connection.read_response()?;
+        self.update_state_from_response(&response);
+        Ok(response)
     }
 
     /// Update client state based on server response
@@ -442,6 +1318,8 @@ impl RaxFtpClient {
                     debug!("Connection closed by server, updating state to Disconnected");
                     self.state = ClientState::Disconnected;
                     self.data_connection = None; // Clear data connection
+                    self.features = None;
+                    self.listing_cache = None;
                 } else {
                     debug!("Logout successful, updating state to Connected");
                     self.state = ClientState::Connected;
@@ -455,3 +1333,395 @@ impl RaxFtpClient {
         }
     }
 }
+
+/// Error for a reply other than the one a command expected
+///
+/// Negative replies map to their typed error; anything else is a protocol error.
+fn unexpected_reply_error(command: &str, response: &FtpResponse) -> RaxFtpClientError {
+    if response.is_negative() {
+        RaxFtpClientError::from_reply(response.clone())
+    } else {
+        RaxFtpClientError::InvalidResponse(format!("unexpected reply to {command}: {response}"))
+    }
+}
+
+/// Error returned when a command needs a logged-in session
+fn not_authenticated_error() -> RaxFtpClientError {
+    RaxFtpClientError::NotAuthenticated(format!(
+        "{} Not authenticated. Please log in first.",
+        crate::responses::status_codes::CLIENT_ERROR_NOT_AUTHENTICATED
+    ))
+}
+
+/// The netrc entry's answer to a login prompt
+fn netrc_answer(entry: &NetrcEntry, prompt: LoginPrompt) -> Option<String> {
+    match prompt {
+        LoginPrompt::Password => entry.password.clone(),
+        LoginPrompt::Account => entry.account.clone(),
+        // The entry is for the server behind the gateway
+        LoginPrompt::GatewayPassword => None,
+    }
+}
+
+/// Extract feature names from a multi-line "211" FEAT reply
+fn parse_feat_response(response: &FtpResponse) -> Vec<String> {
+    if response.code != 211 {
+        return Vec::new();
+    }
+
+    response
+        .body()
+        .iter()
+        .filter(|line| line.starts_with(' '))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|feature| feature.to_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::ProfileConfig;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// A control connection stand-in on loopback
+    ///
+    /// Greets, answers each command with the next scripted reply (which may
+    /// span several lines) and returns every line it received once the client
+    /// hangs up.
+    pub(crate) fn scripted_server(replies: &[&str]) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let replies: Vec<String> = replies.iter().map(|reply| reply.to_string()).collect();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+            stream.write_all(b"220 Scripted server ready\r\n").unwrap();
+
+            let mut received = Vec::new();
+            let mut replies = replies.into_iter();
+            while let Some(Ok(line)) = lines.next() {
+                received.push(line);
+                match replies.next() {
+                    Some(reply) => stream.write_all(reply.as_bytes()).unwrap(),
+                    None => break,
+                }
+            }
+            received.extend(lines.map_while(|line| line.ok()));
+            received
+        });
+        (port, server)
+    }
+
+    /// A client connected to a scripted server, with netrc disabled
+    pub(crate) fn connected_client(port: u16) -> RaxFtpClient {
+        let mut config = ClientConfig {
+            port,
+            ..ClientConfig::default()
+        };
+        config.netrc.enabled = false;
+
+        let mut client = RaxFtpClient::new(config);
+        client.connect_with_retries().unwrap();
+        client
+    }
+
+    #[test]
+    fn test_open_and_close_switch_servers() {
+        let (port, server) = scripted_server(&["221 Goodbye\r\n"]);
+        let mut client = connected_client(port);
+        assert!(client.is_connected());
+
+        let (other_port, other_server) = scripted_server(&["221 Bye\r\n"]);
+        let output = client
+            .execute_command(&FtpCommand::Open("127.0.0.1".to_string(), Some(other_port)))
+            .unwrap();
+        assert!(
+            output
+                .text
+                .starts_with(&format!("Connected to 127.0.0.1:{other_port}"))
+        );
+        assert_eq!(client.server_name(), format!("127.0.0.1:{other_port}"));
+        assert_eq!(server.join().unwrap(), ["QUIT"]);
+
+        let output = client.execute_command(&FtpCommand::Close).unwrap();
+        assert!(
+            output
+                .text
+                .ends_with(&format!("Disconnected from 127.0.0.1:{other_port}"))
+        );
+        assert_eq!(*client.get_state(), ClientState::Disconnected);
+        assert_eq!(other_server.join().unwrap(), ["QUIT"]);
+
+        assert!(matches!(
+            client.execute_command(&FtpCommand::Close),
+            Err(RaxFtpClientError::NotConnected(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_profile_starts_from_the_top_level_settings() {
+        let (port, server) = scripted_server(&[]);
+        let mut defaults = ClientConfig::default();
+        defaults.netrc.enabled = false;
+        defaults.profiles.insert(
+            "acme".to_string(),
+            ProfileConfig {
+                host: Some("ftp.acme.example".to_string()),
+                port: Some(2100),
+                timeout: Some(30),
+                data_mode: Some(DataMode::Active),
+                gateway_login: Some(GatewayLogin::Site),
+                gateway_target: Some("ftp.acme.internal".to_string()),
+                gateway_user: Some("acme-gw".to_string()),
+                gateway_password: Some("acme-secret".to_string()),
+                local_directory: Some("./acme_root".to_string()),
+                host_name: Some("Acme".to_string()),
+                ..ProfileConfig::default()
+            },
+        );
+        defaults.profiles.insert(
+            "partner".to_string(),
+            ProfileConfig {
+                port: Some(port),
+                ..ProfileConfig::default()
+            },
+        );
+
+        // As after `--profile acme`
+        let acme = defaults.profile("acme").unwrap();
+        let mut client = RaxFtpClient::with_defaults(acme, defaults.clone());
+        assert!(client.profiles_text().contains(&format!(
+            "  partner          127.0.0.1:{port} ({})",
+            defaults.data_mode
+        )));
+
+        client.open("partner", None).unwrap();
+        let config = &client.config;
+        assert_eq!(config.profile_name.as_deref(), Some("partner"));
+        assert_eq!(config.host, defaults.host);
+        assert_eq!(config.port, port);
+        assert_eq!(config.timeout, defaults.timeout);
+        assert_eq!(config.data_mode, defaults.data_mode);
+        assert_eq!(config.gateway_login, GatewayLogin::None);
+        assert_eq!(config.gateway_target, None);
+        assert_eq!(config.gateway_user, None);
+        assert_eq!(config.gateway_password, None);
+        assert_eq!(config.local_directory, defaults.local_directory);
+        assert_eq!(client.server_name(), "partner");
+        drop(client);
+        assert!(server.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_login_without_a_prompt_needs_netrc() {
+        let (port, server) = scripted_server(&[]);
+        let mut client = connected_client(port);
+        assert!(matches!(
+            client.execute_command(&FtpCommand::Login(Some("bob".to_string()))),
+            Err(RaxFtpClientError::NotAuthenticated(_))
+        ));
+        drop(client);
+        // Nothing was sent, so the server is not left waiting for PASS
+        assert!(server.join().unwrap().is_empty());
+
+        let directory = std::env::temp_dir().join(format!("rax-netrc-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let netrc_path = directory.join("netrc");
+        std::fs::write(&netrc_path, "machine 127.0.0.1 login bob password s3cret\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&netrc_path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        let (port, server) = scripted_server(&["331 Password required\r\n", "230 Logged in\r\n"]);
+        let mut client = connected_client(port);
+        client.config.netrc.enabled = true;
+        client.config.netrc.path = Some(netrc_path.display().to_string());
+        client
+            .execute_command(&FtpCommand::Login(Some("bob".to_string())))
+            .unwrap();
+        assert!(client.is_authenticated());
+        drop(client);
+        assert_eq!(server.join().unwrap(), ["USER bob", "PASS s3cret"]);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_login_stopping_at_331_fails() {
+        let (port, server) = scripted_server(&["331 Password required\r\n"]);
+        let mut client = connected_client(port);
+        assert!(matches!(
+            client.login("bob", |_| Ok(None)),
+            Err(RaxFtpClientError::NotAuthenticated(_))
+        ));
+        assert!(!client.is_authenticated());
+        drop(client);
+        assert_eq!(server.join().unwrap(), ["USER bob"]);
+    }
+
+    /// A connected client logging in to `real` through a gateway
+    fn gateway_client(
+        port: u16,
+        style: GatewayLogin,
+        gateway_user: Option<&str>,
+        gateway_password: Option<&str>,
+    ) -> RaxFtpClient {
+        let mut client = connected_client(port);
+        client.config.gateway_login = style;
+        client.config.gateway_target = Some("real".to_string());
+        client.config.gateway_user = gateway_user.map(str::to_string);
+        client.config.gateway_password = gateway_password.map(str::to_string);
+        client
+    }
+
+    #[test]
+    fn test_gateway_login_user_at_host() {
+        let (port, server) = scripted_server(&[
+            "331 Gateway password required\r\n",
+            "230 Gateway login ok\r\n",
+            "331 Password required\r\n",
+            "230 Logged in\r\n",
+        ]);
+        let mut client = gateway_client(
+            port,
+            GatewayLogin::UserAtHost,
+            Some("gwuser"),
+            Some("gwpass"),
+        );
+        client
+            .login("bob", |_| Ok(Some("s3cret".to_string())))
+            .unwrap();
+        assert!(client.is_authenticated());
+        drop(client);
+        assert_eq!(
+            server.join().unwrap(),
+            ["USER gwuser", "PASS gwpass", "USER bob@real", "PASS s3cret"]
+        );
+    }
+
+    #[test]
+    fn test_gateway_login_site() {
+        let (port, server) = scripted_server(&[
+            "200 Connected to real\r\n",
+            "331 Password required\r\n",
+            "230 Logged in\r\n",
+        ]);
+        let mut client = gateway_client(port, GatewayLogin::Site, None, None);
+        client
+            .login("bob", |_| Ok(Some("s3cret".to_string())))
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            server.join().unwrap(),
+            ["SITE real", "USER bob", "PASS s3cret"]
+        );
+    }
+
+    #[test]
+    fn test_gateway_login_open() {
+        let (port, server) = scripted_server(&[
+            "220 Connected to real\r\n",
+            "331 Password required\r\n",
+            "230 Logged in\r\n",
+        ]);
+        let mut client = gateway_client(port, GatewayLogin::Open, None, None);
+        client
+            .login("bob", |_| Ok(Some("s3cret".to_string())))
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            server.join().unwrap(),
+            ["OPEN real", "USER bob", "PASS s3cret"]
+        );
+    }
+
+    #[test]
+    fn test_gateway_password_is_asked_for_when_unset() {
+        let (port, server) = scripted_server(&[
+            "331 Gateway password required\r\n",
+            "230 Gateway login ok\r\n",
+            "331 Password required\r\n",
+            "230 Logged in\r\n",
+        ]);
+        let mut client = gateway_client(port, GatewayLogin::UserAtHost, Some("gwuser"), None);
+        let mut prompts = Vec::new();
+        client
+            .login("bob", |prompt| {
+                prompts.push(prompt);
+                Ok(Some(match prompt {
+                    LoginPrompt::GatewayPassword => "gwpass".to_string(),
+                    _ => "s3cret".to_string(),
+                }))
+            })
+            .unwrap();
+        assert_eq!(
+            prompts,
+            [LoginPrompt::GatewayPassword, LoginPrompt::Password]
+        );
+        drop(client);
+        assert_eq!(
+            server.join().unwrap(),
+            ["USER gwuser", "PASS gwpass", "USER bob@real", "PASS s3cret"]
+        );
+
+        // USER cannot prompt, so nothing but the gateway USER goes out
+        let (port, server) = scripted_server(&["331 Gateway password required\r\n"]);
+        let mut client = gateway_client(port, GatewayLogin::UserAtHost, Some("gwuser"), None);
+        assert!(matches!(
+            client.execute_command(&FtpCommand::User("bob".to_string())),
+            Err(RaxFtpClientError::NotAuthenticated(_))
+        ));
+        assert_eq!(*client.get_state(), ClientState::Connected);
+        drop(client);
+        assert_eq!(server.join().unwrap(), ["USER gwuser"]);
+    }
+
+    #[test]
+    fn test_unknown_commands_are_not_sent() {
+        let (port, server) = scripted_server(&[]);
+        let mut client = connected_client(port);
+        assert!(matches!(
+            client.execute_command(&parse_command("FROBNICATE now")),
+            Err(RaxFtpClientError::InvalidCommand(_))
+        ));
+        drop(client);
+        assert!(server.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quote_reads_every_reply_to_the_command() {
+        let (port, server) = scripted_server(&[
+            "211-Status of scripted server\r\n Connected from 127.0.0.1\r\n211 End of status\r\n",
+            "150 Starting\r\n226 Finished\r\n",
+            "200 NOOP ok\r\n",
+        ]);
+        let mut client = connected_client(port);
+
+        let output = client
+            .execute_command(&parse_command("QUOTE STAT"))
+            .unwrap();
+        let reply = output.reply.as_ref().unwrap();
+        assert_eq!(reply.code, 211);
+        assert_eq!(reply.body(), [" Connected from 127.0.0.1"]);
+
+        // A preliminary reply is followed by the final one
+        let output = client
+            .execute_command(&parse_command("QUOTE XRUN job"))
+            .unwrap();
+        assert_eq!(output.text, "150 Starting\n226 Finished");
+        assert_eq!(output.reply.unwrap().code, 226);
+
+        // Nothing is left unread for the next command
+        let output = client
+            .execute_command(&parse_command("QUOTE NOOP"))
+            .unwrap();
+        assert_eq!(output.reply.unwrap().code, 200);
+        drop(client);
+        assert_eq!(server.join().unwrap(), ["STAT", "XRUN job", "NOOP"]);
+    }
+}
@@ -1,5 +1,7 @@
 //! FTP Command definitions
 
+use crate::config::OutputFormat;
+
 /// FTP commands supported by the RAX FTP Client
 #[derive(Debug, Clone, PartialEq)]
 pub enum FtpCommand {
@@ -53,8 +55,106 @@ pub enum FtpCommand {
 
     /// RMD - Remove directory on server  
     Rmd(String),
+
+    /// MDTM - Show modification time of a remote file
+    Mdtm(String),
+
+    /// OUTPUT - Switch between text and JSON output (client-side only)
+    Output(OutputFormat),
+
+    /// OPEN - Connect to another server or profile
+    Open(String, Option<u16>),
+
+    /// CLOSE - Disconnect without exiting
+    Close,
+
+    /// RECONNECT - Reconnect to the current server
+    Reconnect,
+
+    /// PROFILES - List configured server profiles (client-side only)
+    Profiles,
+
+    /// LOGIN - Log in, prompting for the username (if not given) and password
+    Login(Option<String>),
+
+    /// LCD - Change the local directory (client-side only)
+    Lcd(Option<String>),
+
+    /// LPWD - Print the local directory (client-side only)
+    Lpwd,
+
+    /// LLS - List a local directory (client-side only)
+    Lls(Option<String>),
+
+    /// LMKDIR - Create a local directory (client-side only)
+    Lmkdir(String),
+
+    /// ! - Run a local shell command (client-side only)
+    Shell(String),
+
+    /// QUOTE / LITERAL - Send a raw line to the server
+    Quote(String),
+
+    /// SITE - Server-specific command (e.g. SITE CHMOD 644 file)
+    Site(String),
+
+    /// RENAME / MV - Rename or move a remote file (RNFR + RNTO)
+    Rename(String, String),
+
+    /// FXP - Copy a file straight from one profile's server to another's
+    Fxp(FxpEndpoint, FxpEndpoint),
+}
+
+/// One side of an FXP transfer: `<profile>:<path>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxpEndpoint {
+    pub profile: String,
+    pub path: String,
+}
+
+impl std::fmt::Display for FxpEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.profile, self.path)
+    }
 }
 
+/// Command names accepted at the prompt, for completion
+pub const COMMAND_NAMES: &[&str] = &[
+    "USER",
+    "PASS",
+    "LOGIN",
+    "LOGOUT",
+    "STOR",
+    "RETR",
+    "DEL",
+    "LIST",
+    "PWD",
+    "CWD",
+    "PORT",
+    "PASV",
+    "RAX",
+    "MKD",
+    "RMD",
+    "MDTM",
+    "OUTPUT",
+    "OPEN",
+    "CLOSE",
+    "RECONNECT",
+    "PROFILES",
+    "LCD",
+    "LPWD",
+    "LLS",
+    "LMKDIR",
+    "QUOTE",
+    "LITERAL",
+    "SITE",
+    "RENAME",
+    "MV",
+    "FXP",
+    "HELP",
+    "QUIT",
+];
+
 impl FtpCommand {
     /// Convert command to FTP protocol string
     pub fn to_ftp_string(&self) -> String {
@@ -76,12 +176,39 @@ impl FtpCommand {
             FtpCommand::Unknown(cmd) => cmd.clone(),
             FtpCommand::Mkd(dirname) => format!("MKD {dirname}"),
             FtpCommand::Rmd(dirname) => format!("RMD {dirname}"),
+            FtpCommand::Mdtm(filename) => format!("MDTM {filename}"),
+            FtpCommand::Output(format) => format!("OUTPUT {format}"),
+            FtpCommand::Open(host, port) => open_string(host, *port),
+            FtpCommand::Close => "CLOSE".to_string(),
+            FtpCommand::Reconnect => "RECONNECT".to_string(),
+            FtpCommand::Profiles => "PROFILES".to_string(),
+            FtpCommand::Login(Some(username)) => format!("LOGIN {username}"),
+            FtpCommand::Login(None) => "LOGIN".to_string(),
+            FtpCommand::Lcd(path) => with_optional_arg("LCD", path.as_deref()),
+            FtpCommand::Lpwd => "LPWD".to_string(),
+            FtpCommand::Lls(path) => with_optional_arg("LLS", path.as_deref()),
+            FtpCommand::Lmkdir(path) => format!("LMKDIR {path}"),
+            FtpCommand::Shell(command) => format!("!{command}"),
+            FtpCommand::Quote(line) => line.clone(),
+            FtpCommand::Site(args) => format!("SITE {args}"),
+            FtpCommand::Rename(from, to) => format!("RENAME {from} {to}"),
+            FtpCommand::Fxp(source, destination) => format!("FXP {source} {destination}"),
         }
     }
 
     /// Check if command is client-side only
     pub fn is_client_only(&self) -> bool {
-        matches!(self, FtpCommand::Help)
+        matches!(
+            self,
+            FtpCommand::Help
+                | FtpCommand::Output(_)
+                | FtpCommand::Profiles
+                | FtpCommand::Lcd(_)
+                | FtpCommand::Lpwd
+                | FtpCommand::Lls(_)
+                | FtpCommand::Lmkdir(_)
+                | FtpCommand::Shell(_)
+        )
     }
 }
 
@@ -105,6 +232,94 @@ impl std::fmt::Display for FtpCommand {
             FtpCommand::Unknown(cmd) => write!(f, "UNKNOWN({cmd})"),
             FtpCommand::Mkd(dirname) => write!(f, "MKD {dirname}"),
             FtpCommand::Rmd(dirname) => write!(f, "RMD {dirname}"),
+            FtpCommand::Mdtm(filename) => write!(f, "MDTM {filename}"),
+            FtpCommand::Output(format) => write!(f, "OUTPUT {format}"),
+            FtpCommand::Open(host, port) => write!(f, "{}", open_string(host, *port)),
+            FtpCommand::Close => write!(f, "CLOSE"),
+            FtpCommand::Reconnect => write!(f, "RECONNECT"),
+            FtpCommand::Profiles => write!(f, "PROFILES"),
+            FtpCommand::Login(Some(username)) => write!(f, "LOGIN {username}"),
+            FtpCommand::Login(None) => write!(f, "LOGIN"),
+            FtpCommand::Lcd(path) => write!(f, "{}", with_optional_arg("LCD", path.as_deref())),
+            FtpCommand::Lpwd => write!(f, "LPWD"),
+            FtpCommand::Lls(path) => write!(f, "{}", with_optional_arg("LLS", path.as_deref())),
+            FtpCommand::Lmkdir(path) => write!(f, "LMKDIR {path}"),
+            FtpCommand::Shell(command) => write!(f, "!{command}"),
+            FtpCommand::Quote(line) => write!(f, "QUOTE {}", redact_secrets(line)),
+            FtpCommand::Site(args) => write!(f, "SITE {args}"),
+            FtpCommand::Rename(from, to) => write!(f, "RENAME {from} {to}"),
+            FtpCommand::Fxp(source, destination) => write!(f, "FXP {source} {destination}"),
+        }
+    }
+}
+
+fn with_optional_arg(name: &str, arg: Option<&str>) -> String {
+    match arg {
+        Some(arg) => format!("{name} {arg}"),
+        None => name.to_string(),
+    }
+}
+
+fn open_string(host: &str, port: Option<u16>) -> String {
+    match port {
+        Some(port) => format!("OPEN {host} {port}"),
+        None => format!("OPEN {host}"),
+    }
+}
+
+/// Check whether a command line carries a password or account (PASS/ACCT)
+pub fn is_secret_command(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    let mut verb = words.next().unwrap_or("");
+    // QUOTE PASS ... sends a password too
+    if is_quote_verb(verb) {
+        verb = words.next().unwrap_or("");
+    }
+    verb.eq_ignore_ascii_case("PASS") || verb.eq_ignore_ascii_case("ACCT")
+}
+
+/// Command line with PASS/ACCT arguments hidden, safe to log
+pub fn redact_secrets(line: &str) -> String {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if !is_secret_command(line) {
+        return line.trim_end().to_string();
+    }
+
+    // Keep the verbs ("PASS" or "QUOTE PASS"), hide the rest
+    let verbs = if is_quote_verb(words[0]) { 2 } else { 1 };
+    if words.len() > verbs {
+        format!("{} [hidden]", words[..verbs].join(" "))
+    } else {
+        line.trim_end().to_string()
+    }
+}
+
+fn is_quote_verb(word: &str) -> bool {
+    word.eq_ignore_ascii_case("QUOTE") || word.eq_ignore_ascii_case("LITERAL")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(redact_secrets("PASS s3cret\r\n"), "PASS [hidden]");
+        assert_eq!(redact_secrets("acct billing"), "acct [hidden]");
+        assert_eq!(redact_secrets("USER alice"), "USER alice");
+        assert_eq!(redact_secrets("PASSIVE"), "PASSIVE");
+        assert_eq!(redact_secrets("QUOTE PASS s3cret"), "QUOTE PASS [hidden]");
+        assert!(is_secret_command("literal acct billing"));
+    }
+
+    #[test]
+    fn test_command_names_are_parsed() {
+        for name in COMMAND_NAMES {
+            let command = crate::commands::parse_command(&format!("{name} text"));
+            assert!(
+                !matches!(&command, FtpCommand::Unknown(message) if message.starts_with("Unknown command")),
+                "{name} is not recognised by the parser"
+            );
         }
     }
 }
@@ -4,7 +4,8 @@
 pub fn get_help_text() -> String {
     String::from(
         "Available commands:
-  USER <username>   - Authenticate with username
+  LOGIN [username]  - Log in, prompting for username and hidden password
+  USER <username>   - Authenticate with username (bare USER prompts like LOGIN)
   PASS <password>   - Provide password
   STOR <filename>   - Upload file to server
   RETR <filename>   - Download file from server
@@ -16,9 +17,26 @@ pub fn get_help_text() -> String {
   DEL <filename>    - Delete file on server
   LOGOUT            - Log out current user
   RAX               - Custom server command
+  OPEN <host> [port] - Connect to another server (port defaults to 21)
+  OPEN <profile>    - Connect using a profile from config.toml
+  PROFILES          - List configured profiles
+  CLOSE             - Disconnect without exiting
+  RECONNECT         - Reconnect to the current server
   QUIT              - Disconnect and exit
+  RENAME <from> <to> - Rename or move a remote file (alias MV; quote names with spaces)
+  FXP <profile>:<path> <profile>:<path> - Copy a file directly between two profiles' servers
+                      (each profile logs in from its ~/.netrc entry)
   MKD <directory>   - Create directory on server
   RMD <directory>   - Remove directory on server
+  MDTM <filename>   - Show modification time of a remote file
+  OUTPUT <text|json> - Switch between text and JSON output
+  LCD [directory]   - Change the local directory (no argument: back to the configured one)
+  LPWD              - Print the local directory
+  LLS [directory]   - List a local directory
+  LMKDIR <directory> - Create a local directory
+  !<command>        - Run a shell command in the local directory (bare ! starts a shell)
+  QUOTE <line>      - Send a raw command line and show the full reply (alias LITERAL)
+  SITE <args>       - Server-specific command, e.g. SITE CHMOD 644 file.txt
   HELP              - Show this help message
 
 Data Transfer Information:
@@ -4,9 +4,11 @@
 
 pub mod command;
 pub mod help;
+pub mod output;
 pub mod parser;
 
 // Re-export the main types for easier importing
-pub use command::FtpCommand;
+pub use command::{COMMAND_NAMES, FtpCommand, FxpEndpoint, is_secret_command, redact_secrets};
 pub use help::get_help_text;
+pub use output::{CommandData, CommandOutput};
 pub use parser::parse_command;
@@ -0,0 +1,90 @@
+//! Structured results of executed commands
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::error::{RaxFtpClientError, Result};
+use crate::responses::FtpResponse;
+use crate::terminal::listing::DirectoryEntry;
+use crate::transfer::progress::TransferSummary;
+
+/// Result of executing a single command
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// Human-readable output, as shown in text mode
+    pub text: String,
+
+    /// Final server reply, if the command reached the server
+    pub reply: Option<FtpResponse>,
+
+    /// Parsed data extracted from the command's result
+    pub data: CommandData,
+}
+
+/// Parsed data carried by a command result
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommandData {
+    /// Nothing beyond the reply itself
+    None,
+
+    /// Entries of a directory listing
+    Listing { entries: Vec<DirectoryEntry> },
+
+    /// Current remote working directory
+    WorkingDirectory { path: String },
+
+    /// Summary of a completed file transfer
+    Transfer(TransferSummary),
+
+    /// Modification time of a remote file
+    ModificationTime { file: String, time: DateTime<Utc> },
+}
+
+impl CommandOutput {
+    /// Output of a command handled entirely on the client side
+    pub fn local(text: String) -> Self {
+        Self {
+            text,
+            reply: None,
+            data: CommandData::None,
+        }
+    }
+
+    /// Output consisting of a single server reply
+    pub fn from_reply(response: FtpResponse) -> Self {
+        Self {
+            text: response.to_string(),
+            reply: Some(response),
+            data: CommandData::None,
+        }
+    }
+
+    /// Output made of several lines, with `final_reply` as the reply that concluded the command
+    pub fn from_lines(lines: Vec<String>, final_reply: &FtpResponse) -> Self {
+        Self {
+            text: lines.join("\n"),
+            reply: Some(final_reply.clone()),
+            data: CommandData::None,
+        }
+    }
+
+    /// Attach parsed data to the output
+    pub fn with_data(mut self, data: CommandData) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Check whether the final reply (if any) reports success
+    pub fn is_success(&self) -> bool {
+        self.reply.as_ref().is_none_or(|reply| !reply.is_negative())
+    }
+
+    /// Turn output that ended in a negative reply into the matching error
+    pub fn into_result(self) -> Result<Self> {
+        match &self.reply {
+            Some(reply) if reply.is_negative() => Err(RaxFtpClientError::from_reply(reply.clone())),
+            _ => Ok(self),
+        }
+    }
+}
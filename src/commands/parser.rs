@@ -1,6 +1,7 @@
 //! Command parsing functionality
 
-use super::FtpCommand;
+use super::{FtpCommand, FxpEndpoint};
+use crate::config::OutputFormat;
 
 /// Parse user input into FtpCommand
 pub fn parse_command(input: &str) -> FtpCommand {
@@ -10,6 +11,11 @@ pub fn parse_command(input: &str) -> FtpCommand {
         return FtpCommand::Unknown("Empty command".to_string());
     }
 
+    // "!command" runs a local shell command
+    if let Some(shell_command) = trimmed.strip_prefix('!') {
+        return FtpCommand::Shell(shell_command.trim().to_string());
+    }
+
     let mut parts = trimmed.splitn(2, char::is_whitespace);
     let cmd = parts.next().unwrap_or("").to_uppercase();
     let arg = parts.next().unwrap_or("").trim();
@@ -18,11 +24,19 @@ pub fn parse_command(input: &str) -> FtpCommand {
         "QUIT" => FtpCommand::Quit,
         "USER" => {
             if arg.is_empty() {
-                FtpCommand::Unknown("USER requires username".to_string())
+                // Prompt for the username instead
+                FtpCommand::Login(None)
             } else {
                 FtpCommand::User(arg.to_string())
             }
         }
+        "LOGIN" => {
+            if arg.is_empty() {
+                FtpCommand::Login(None)
+            } else {
+                FtpCommand::Login(Some(arg.to_string()))
+            }
+        }
         "PASS" => {
             if arg.is_empty() {
                 FtpCommand::Unknown("PASS requires password".to_string())
@@ -79,6 +93,65 @@ pub fn parse_command(input: &str) -> FtpCommand {
                 FtpCommand::Rmd(arg.to_string())
             }
         }
+        "MDTM" => {
+            if arg.is_empty() {
+                FtpCommand::Unknown("MDTM requires filename".to_string())
+            } else {
+                FtpCommand::Mdtm(arg.to_string())
+            }
+        }
+        "OUTPUT" => match arg.to_lowercase().as_str() {
+            "text" => FtpCommand::Output(OutputFormat::Text),
+            "json" => FtpCommand::Output(OutputFormat::Json),
+            _ => FtpCommand::Unknown("OUTPUT requires 'text' or 'json'".to_string()),
+        },
+        "OPEN" => {
+            let mut args = arg.split_whitespace();
+            match (args.next(), args.next(), args.next()) {
+                (Some(host), None, None) => FtpCommand::Open(host.to_string(), None),
+                (Some(host), Some(port), None) => match port.parse() {
+                    Ok(port) => FtpCommand::Open(host.to_string(), Some(port)),
+                    Err(_) => FtpCommand::Unknown(format!("Invalid port: {port}")),
+                },
+                _ => FtpCommand::Unknown("OPEN requires host [port] or profile".to_string()),
+            }
+        }
+        "CLOSE" => FtpCommand::Close,
+        "RECONNECT" => FtpCommand::Reconnect,
+        "PROFILES" => FtpCommand::Profiles,
+        "LCD" => FtpCommand::Lcd((!arg.is_empty()).then(|| arg.to_string())),
+        "LPWD" => FtpCommand::Lpwd,
+        "LLS" => FtpCommand::Lls((!arg.is_empty()).then(|| arg.to_string())),
+        "LMKDIR" => {
+            if arg.is_empty() {
+                FtpCommand::Unknown("LMKDIR requires directory name".to_string())
+            } else {
+                FtpCommand::Lmkdir(arg.to_string())
+            }
+        }
+        "QUOTE" | "LITERAL" => {
+            if arg.is_empty() {
+                FtpCommand::Unknown(format!("{cmd} requires a command line"))
+            } else {
+                FtpCommand::Quote(arg.to_string())
+            }
+        }
+        "SITE" => {
+            if arg.is_empty() {
+                FtpCommand::Unknown("SITE requires arguments".to_string())
+            } else {
+                FtpCommand::Site(arg.to_string())
+            }
+        }
+        "RENAME" | "MV" => match split_arguments(arg).as_slice() {
+            [from, to] => FtpCommand::Rename(from.clone(), to.clone()),
+            _ => FtpCommand::Unknown(format!("{cmd} requires <from> <to>")),
+        },
+        "FXP" => parse_fxp(arg).unwrap_or_else(|| {
+            FtpCommand::Unknown(
+                "FXP requires <src-profile>:<path> <dst-profile>:<path>".to_string(),
+            )
+        }),
         "LIST" => FtpCommand::List,
         "PWD" => FtpCommand::Pwd,
         "PASV" => FtpCommand::Pasv,
@@ -88,3 +161,118 @@ pub fn parse_command(input: &str) -> FtpCommand {
         _ => FtpCommand::Unknown(format!("Unknown command: {cmd}")),
     }
 }
+
+/// Parse the `<src-profile>:<path> <dst-profile>:<path>` arguments of FXP
+fn parse_fxp(arg: &str) -> Option<FtpCommand> {
+    match split_arguments(arg).as_slice() {
+        [source, destination] => Some(FtpCommand::Fxp(
+            fxp_endpoint(source)?,
+            fxp_endpoint(destination)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Parse `<profile>:<path>`; both parts must be non-empty
+fn fxp_endpoint(arg: &str) -> Option<FxpEndpoint> {
+    let (profile, path) = arg.split_once(':')?;
+    (!profile.is_empty() && !path.is_empty()).then(|| FxpEndpoint {
+        profile: profile.to_string(),
+        path: path.to_string(),
+    })
+}
+
+/// Split arguments on whitespace, keeping "double-quoted" names together
+fn split_arguments(input: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_argument = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_argument = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    has_argument = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_argument = true;
+            }
+        }
+    }
+    if has_argument {
+        arguments.push(current);
+    }
+
+    arguments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_connection_commands() {
+        assert_eq!(
+            parse_command("open ftp.example.com 2121"),
+            FtpCommand::Open("ftp.example.com".to_string(), Some(2121))
+        );
+        assert_eq!(
+            parse_command("OPEN ftp.example.com"),
+            FtpCommand::Open("ftp.example.com".to_string(), None)
+        );
+        assert!(matches!(
+            parse_command("OPEN ftp.example.com port"),
+            FtpCommand::Unknown(_)
+        ));
+        assert!(matches!(parse_command("OPEN"), FtpCommand::Unknown(_)));
+        assert_eq!(parse_command("close"), FtpCommand::Close);
+        assert_eq!(parse_command("RECONNECT"), FtpCommand::Reconnect);
+        assert_eq!(
+            FtpCommand::Open("ftp.example.com".to_string(), Some(2121)).to_string(),
+            "OPEN ftp.example.com 2121"
+        );
+    }
+
+    #[test]
+    fn test_parse_rename_with_quoted_names() {
+        assert_eq!(
+            parse_command("mv \"old name.txt\" new.txt"),
+            FtpCommand::Rename("old name.txt".to_string(), "new.txt".to_string())
+        );
+        assert_eq!(
+            parse_command("RENAME a.tmp a"),
+            FtpCommand::Rename("a.tmp".to_string(), "a".to_string())
+        );
+        assert!(matches!(
+            parse_command("RENAME onlyone"),
+            FtpCommand::Unknown(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_fxp_endpoints() {
+        let endpoint = |profile: &str, path: &str| FxpEndpoint {
+            profile: profile.to_string(),
+            path: path.to_string(),
+        };
+        assert_eq!(
+            parse_command("FXP acme:/pub/big.iso \"backup:/in coming/big.iso\""),
+            FtpCommand::Fxp(
+                endpoint("acme", "/pub/big.iso"),
+                endpoint("backup", "/in coming/big.iso")
+            )
+        );
+        assert!(matches!(
+            parse_command("FXP acme:/pub/big.iso /tmp/big.iso"),
+            FtpCommand::Unknown(_)
+        ));
+    }
+}
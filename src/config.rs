@@ -3,7 +3,10 @@
 //! Loads configuration from config.toml with environment variable overrides.
 
 use config::{Config, Environment, File};
+use log::warn;
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 
 /// Complete client configuration
 #[derive(Debug, Deserialize, Clone)]
@@ -18,9 +21,34 @@ pub struct ClientConfig {
     /// Connection timeout in seconds
     pub timeout: u64,
 
-    /// Maximum number of retry attempts
+    /// Maximum number of attempts for connecting (and retried transfers)
     pub max_retries: u32,
 
+    /// Backoff between attempts
+    #[serde(default)]
+    pub retry: RetryConfig,
+
+    /// SOCKS5 or HTTP CONNECT proxy for the control and passive data connections
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+
+    /// IP versions used to reach the server (any, ipv4 or ipv6)
+    #[serde(default)]
+    pub ip_version: IpVersion,
+
+    /// Login sequence when `host` is an FTP gateway (none, user_at_host, site or open)
+    #[serde(default)]
+    pub gateway_login: GatewayLogin,
+
+    /// Server the gateway should connect to, e.g. "ftp.partner.example"
+    pub gateway_target: Option<String>,
+
+    /// The gateway's own username, sent before the target is named (optional)
+    pub gateway_user: Option<String>,
+
+    /// The gateway's own password (optional)
+    pub gateway_password: Option<String>,
+
     // ═══ CLIENT SETTINGS ═══
     /// Local directory for file operations
     pub local_directory: String,
@@ -29,9 +57,352 @@ pub struct ClientConfig {
     pub data_port_start: u16,
     pub data_port_end: u16,
 
+    /// Data connection mode used when none was chosen with PASV or PORT
+    #[serde(default)]
+    pub data_mode: DataMode,
+
+    /// Passive connect and data read/write timeout in seconds
+    #[serde(default = "default_data_timeout")]
+    pub data_timeout: u64,
+
+    /// Seconds to wait for the server to connect in active mode
+    #[serde(default = "default_data_accept_timeout")]
+    pub data_accept_timeout: u64,
+
+    /// IP advertised with PORT in active mode, e.g. the router's public address behind NAT
+    pub active_external_ip: Option<IpAddr>,
+
+    /// Local address the active mode listener binds to (all interfaces if unset)
+    pub active_bind_address: Option<IpAddr>,
+
+    /// Which IP to connect to after PASV (auto, server or control)
+    #[serde(default)]
+    pub passive_address: PassiveAddress,
+
+    /// Set downloaded files' mtime from the server's MDTM reply
+    #[serde(default = "default_true")]
+    pub preserve_download_mtime: bool,
+
+    /// Send MFMT after uploads so the remote file keeps the local mtime
+    #[serde(default = "default_true")]
+    pub preserve_upload_mtime: bool,
+
+    /// How transfer progress is displayed (auto, bar, plain, quiet or json)
+    #[serde(default)]
+    pub progress: ProgressStyle,
+
+    /// How command results are printed (text or json)
+    #[serde(default)]
+    pub output: OutputFormat,
+
+    /// Automatic login from a netrc file
+    #[serde(default)]
+    pub netrc: NetrcConfig,
+
     // ═══ OPTIONAL SETTINGS ═══
     /// Friendly name for server display (optional)
     pub host_name: Option<String>,
+
+    /// Named server profiles, selected with `--profile` or `OPEN <profile>`
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+
+    /// Name of the profile these settings came from, if any
+    #[serde(skip)]
+    pub profile_name: Option<String>,
+}
+
+/// Per-server settings from a `[profiles.<name>]` table
+///
+/// Unset values are inherited from the top-level configuration.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ProfileConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub timeout: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub ip_version: Option<IpVersion>,
+    pub gateway_login: Option<GatewayLogin>,
+    pub gateway_target: Option<String>,
+    pub gateway_user: Option<String>,
+    pub gateway_password: Option<String>,
+    pub local_directory: Option<String>,
+    pub data_port_start: Option<u16>,
+    pub data_port_end: Option<u16>,
+    pub data_mode: Option<DataMode>,
+    pub data_timeout: Option<u64>,
+    pub data_accept_timeout: Option<u64>,
+    pub active_external_ip: Option<IpAddr>,
+    pub active_bind_address: Option<IpAddr>,
+    pub passive_address: Option<PassiveAddress>,
+    pub preserve_download_mtime: Option<bool>,
+    pub preserve_upload_mtime: Option<bool>,
+    /// Display name; defaults to the profile name
+    pub host_name: Option<String>,
+}
+
+/// `[netrc]` settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct NetrcConfig {
+    /// Log in automatically when the netrc file has an entry for the server
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Netrc file to use instead of ~/.netrc (optional)
+    pub path: Option<String>,
+}
+
+impl Default for NetrcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+        }
+    }
+}
+
+/// `[retry]` settings: exponential backoff with jitter
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryConfig {
+    /// Delay before the first retry in milliseconds
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub base_delay_ms: u64,
+
+    /// Factor the delay grows by after each failed attempt
+    #[serde(default = "default_retry_multiplier")]
+    pub multiplier: f64,
+
+    /// Upper bound for any delay in milliseconds
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub max_delay_ms: u64,
+
+    /// Fraction (0.0 - 1.0) each delay is randomly moved up or down by
+    #[serde(default = "default_retry_jitter")]
+    pub jitter: f64,
+
+    /// Retry transfers that fail with 425/426/450/451 or a broken data connection
+    #[serde(default)]
+    pub transfers: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            base_delay_ms: default_retry_base_delay_ms(),
+            multiplier: default_retry_multiplier(),
+            max_delay_ms: default_retry_max_delay_ms(),
+            jitter: default_retry_jitter(),
+            transfers: false,
+        }
+    }
+}
+
+/// `[proxy]` settings
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ProxyConfig {
+    /// Proxy protocol (none, socks5 or http)
+    #[serde(default)]
+    pub kind: ProxyKind,
+
+    /// Proxy host name or address
+    pub host: Option<String>,
+
+    /// Proxy port (1080 for SOCKS5, 8080 for HTTP if unset)
+    pub port: Option<u16>,
+
+    /// Username for proxy authentication (optional)
+    pub username: Option<String>,
+
+    /// Password for proxy authentication (optional)
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    /// Whether connections go through a proxy
+    pub fn is_enabled(&self) -> bool {
+        self.kind != ProxyKind::None
+    }
+}
+
+/// Proxy protocol
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyKind {
+    /// Connect directly
+    #[default]
+    None,
+    /// SOCKS5 (RFC 1928) with optional username/password authentication
+    Socks5,
+    /// HTTP CONNECT tunnel with optional Basic authentication
+    Http,
+}
+
+impl ProxyKind {
+    /// Port used when `[proxy] port` is not set
+    pub fn default_port(self) -> u16 {
+        match self {
+            ProxyKind::Http => 8080,
+            ProxyKind::None | ProxyKind::Socks5 => 1080,
+        }
+    }
+}
+
+impl std::fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProxyKind::None => write!(f, "no"),
+            ProxyKind::Socks5 => write!(f, "SOCKS5"),
+            ProxyKind::Http => write!(f, "HTTP"),
+        }
+    }
+}
+
+/// Data connection mode
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DataMode {
+    /// Client connects to the address from the server's PASV reply
+    #[default]
+    Passive,
+    /// Client listens on the data port range and sends PORT
+    Active,
+}
+
+impl std::fmt::Display for DataMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataMode::Passive => write!(f, "passive"),
+            DataMode::Active => write!(f, "active"),
+        }
+    }
+}
+
+/// IP versions allowed for the control connection
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    /// Every resolved address, IPv6 and IPv4 raced (Happy Eyeballs)
+    #[default]
+    Any,
+    /// IPv4 addresses only
+    Ipv4,
+    /// IPv6 addresses only
+    Ipv6,
+}
+
+impl IpVersion {
+    /// Whether an address may be used
+    pub fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            IpVersion::Any => true,
+            IpVersion::Ipv4 => ip.is_ipv4(),
+            IpVersion::Ipv6 => ip.is_ipv6(),
+        }
+    }
+}
+
+impl std::fmt::Display for IpVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpVersion::Any => write!(f, "IP"),
+            IpVersion::Ipv4 => write!(f, "IPv4"),
+            IpVersion::Ipv6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// How to name the real server to an FTP application-level gateway
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewayLogin {
+    /// No gateway: log in to `host` directly
+    #[default]
+    None,
+    /// `USER user@target`
+    UserAtHost,
+    /// `SITE target`, then `USER user`
+    Site,
+    /// `OPEN target`, then `USER user`
+    Open,
+}
+
+/// Where passive data connections go
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PassiveAddress {
+    /// The server's address, unless it reports a private or loopback IP
+    /// while the control connection goes to a public one
+    #[default]
+    Auto,
+    /// Always the IP from the 227 reply
+    Server,
+    /// Always the control connection's server IP
+    Control,
+}
+
+/// Transfer progress display style
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressStyle {
+    /// Bars on a terminal, plain lines when stdout is piped
+    #[default]
+    Auto,
+    /// Redrawn progress bars sized to the terminal width
+    Bar,
+    /// Periodic plain-text lines (every 10%)
+    Plain,
+    /// No progress output at all
+    Quiet,
+    /// JSON lines on stderr, one per progress event
+    Json,
+}
+
+/// Command result output format
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable replies and tables
+    #[default]
+    Text,
+    /// One JSON object per command on stdout
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_data_timeout() -> u64 {
+    30
+}
+
+fn default_data_accept_timeout() -> u64 {
+    5
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_retry_multiplier() -> f64 {
+    2.0
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_retry_jitter() -> f64 {
+    0.2
 }
 
 impl ClientConfig {
@@ -42,16 +413,27 @@ impl ClientConfig {
         ];
 
         let mut last_error = None;
+        let profile_overrides = profile_variables(std::env::vars());
 
         for config_path in &config_paths {
-            match Config::builder()
+            let mut builder = Config::builder()
                 .add_source(File::with_name(config_path))
-                .add_source(Environment::with_prefix("RAX_FTP").separator("_"))
-                .build()
-            {
+                .add_source(environment());
+            for (key, value) in &profile_overrides {
+                builder = builder.set_override(key, value.as_str())?;
+            }
+
+            match builder.build() {
                 Ok(settings) => {
                     let config: ClientConfig = settings.try_deserialize()?;
                     config.validate(config_path)?;
+                    for name in config.profiles.keys() {
+                        if let Some(profile) = config.profile(name) {
+                            profile.validate_values().map_err(|e| {
+                                config::ConfigError::Message(format!("Profile '{name}': {e}"))
+                            })?;
+                        }
+                    }
                     return Ok(config);
                 }
                 Err(e) => {
@@ -66,11 +448,103 @@ impl ClientConfig {
         );
     }
 
+    /// Configuration for a named profile, with the local directory created if missing
+    pub fn with_profile(&self, name: &str) -> Result<Self, config::ConfigError> {
+        let config = self
+            .profile(name)
+            .ok_or_else(|| config::ConfigError::Message(format!("Unknown profile '{name}'")))?;
+        config.validate_values()?;
+        config.check_local_directory(false)?;
+        Ok(config)
+    }
+
+    /// Apply a profile's settings on top of this configuration, without validation
+    pub fn profile(&self, name: &str) -> Option<Self> {
+        let profile = self.profiles.get(name)?;
+        let mut config = self.clone();
+
+        if let Some(host) = &profile.host {
+            config.host = host.clone();
+        }
+        if let Some(port) = profile.port {
+            config.port = port;
+        }
+        if let Some(timeout) = profile.timeout {
+            config.timeout = timeout;
+        }
+        if let Some(max_retries) = profile.max_retries {
+            config.max_retries = max_retries;
+        }
+        if let Some(ip_version) = profile.ip_version {
+            config.ip_version = ip_version;
+        }
+        if let Some(gateway_login) = profile.gateway_login {
+            config.gateway_login = gateway_login;
+        }
+        if profile.gateway_target.is_some() {
+            config.gateway_target = profile.gateway_target.clone();
+        }
+        if profile.gateway_user.is_some() {
+            config.gateway_user = profile.gateway_user.clone();
+        }
+        if profile.gateway_password.is_some() {
+            config.gateway_password = profile.gateway_password.clone();
+        }
+        if let Some(local_directory) = &profile.local_directory {
+            config.local_directory = local_directory.clone();
+        }
+        if let Some(start) = profile.data_port_start {
+            config.data_port_start = start;
+        }
+        if let Some(end) = profile.data_port_end {
+            config.data_port_end = end;
+        }
+        if let Some(data_mode) = profile.data_mode {
+            config.data_mode = data_mode;
+        }
+        if let Some(timeout) = profile.data_timeout {
+            config.data_timeout = timeout;
+        }
+        if let Some(timeout) = profile.data_accept_timeout {
+            config.data_accept_timeout = timeout;
+        }
+        if profile.active_external_ip.is_some() {
+            config.active_external_ip = profile.active_external_ip;
+        }
+        if profile.active_bind_address.is_some() {
+            config.active_bind_address = profile.active_bind_address;
+        }
+        if let Some(passive_address) = profile.passive_address {
+            config.passive_address = passive_address;
+        }
+        if let Some(preserve) = profile.preserve_download_mtime {
+            config.preserve_download_mtime = preserve;
+        }
+        if let Some(preserve) = profile.preserve_upload_mtime {
+            config.preserve_upload_mtime = preserve;
+        }
+        config.host_name = Some(
+            profile
+                .host_name
+                .clone()
+                .unwrap_or_else(|| name.to_string()),
+        );
+        config.profile_name = Some(name.to_string());
+
+        Some(config)
+    }
+
     /// Validation for configuration values
     fn validate(&self, config_path: &str) -> Result<(), config::ConfigError> {
         // Determine if we're in Docker based on config path
         let is_docker = config_path == "rax-ftp-client/config";
 
+        self.validate_values()?;
+        self.check_local_directory(is_docker)
+    }
+
+    /// Validation for values that do not touch the filesystem
+    fn validate_values(&self) -> Result<(), config::ConfigError> {
         // Basic validations
         if self.host.is_empty() {
             return Err(config::ConfigError::Message("Host cannot be empty".into()));
@@ -84,6 +558,30 @@ impl ClientConfig {
             return Err(config::ConfigError::Message("Timeout cannot be 0".into()));
         }
 
+        if self.data_timeout == 0 || self.data_accept_timeout == 0 {
+            return Err(config::ConfigError::Message(
+                "Data timeouts cannot be 0".into(),
+            ));
+        }
+
+        if self.retry.multiplier < 1.0 || !(0.0..=1.0).contains(&self.retry.jitter) {
+            return Err(config::ConfigError::Message(
+                "retry.multiplier must be at least 1.0 and retry.jitter between 0.0 and 1.0".into(),
+            ));
+        }
+
+        if self.gateway_login != GatewayLogin::None && self.gateway_target.is_none() {
+            return Err(config::ConfigError::Message(
+                "gateway_target must be set when gateway_login is used".into(),
+            ));
+        }
+
+        if self.proxy.is_enabled() && self.proxy.host.as_deref().is_none_or(str::is_empty) {
+            return Err(config::ConfigError::Message(
+                "proxy.host must be set when a proxy kind is configured".into(),
+            ));
+        }
+
         if self.data_port_start >= self.data_port_end {
             return Err(config::ConfigError::Message(
                 "data_port_start must be less than data_port_end".into(),
@@ -96,16 +594,22 @@ impl ClientConfig {
             ));
         }
 
+        Ok(())
+    }
+
+    /// Local directory validation
+    fn check_local_directory(&self, is_docker: bool) -> Result<(), config::ConfigError> {
         // Local directory validation - create if doesn't exist
         let local_dir_path = std::path::Path::new(&self.local_directory);
 
-        if !local_dir_path.exists() && !is_docker {
-            if let Err(e) = std::fs::create_dir_all(local_dir_path) {
-                return Err(config::ConfigError::Message(format!(
-                    "Failed to create local directory '{}': {}",
-                    self.local_directory, e
-                )));
-            }
+        if !local_dir_path.exists()
+            && !is_docker
+            && let Err(e) = std::fs::create_dir_all(local_dir_path)
+        {
+            return Err(config::ConfigError::Message(format!(
+                "Failed to create local directory '{}': {}",
+                self.local_directory, e
+            )));
         }
 
         if local_dir_path.exists() && !local_dir_path.is_dir() && !is_docker {
@@ -125,6 +629,21 @@ impl ClientConfig {
         (self.data_port_start, self.data_port_end)
     }
 
+    /// Host the login is for: the gateway's target, or `host` without a gateway
+    pub fn login_host(&self) -> &str {
+        match (&self.gateway_login, &self.gateway_target) {
+            (GatewayLogin::None, _) | (_, None) => &self.host,
+            // Drop a ":port" suffix and the brackets around IPv6 addresses
+            (_, Some(target)) => match target.strip_prefix('[') {
+                Some(rest) => rest.split(']').next().unwrap_or(rest),
+                None => match target.split_once(':') {
+                    Some((host, port)) if !port.contains(':') => host,
+                    _ => target,
+                },
+            },
+        }
+    }
+
     pub fn display_name(&self) -> String {
         match &self.host_name {
             Some(name) => name.clone(),
@@ -133,6 +652,75 @@ impl ClientConfig {
     }
 }
 
+/// `RAX_FTP_` environment overrides; `__` separates table and key names
+///
+/// Keys keep their own underscores: `RAX_FTP_DATA_MODE` sets `data_mode` and
+/// `RAX_FTP_PROFILES__ACME__DATA_MODE` sets `profiles.acme.data_mode`.
+fn environment() -> Environment {
+    Environment::with_prefix("RAX_FTP")
+        .prefix_separator("_")
+        .separator("__")
+}
+
+/// Settings a `[profiles.<name>]` table accepts
+const PROFILE_KEYS: &[&str] = &[
+    "host",
+    "port",
+    "timeout",
+    "max_retries",
+    "ip_version",
+    "gateway_login",
+    "gateway_target",
+    "gateway_user",
+    "gateway_password",
+    "local_directory",
+    "data_port_start",
+    "data_port_end",
+    "data_mode",
+    "data_timeout",
+    "data_accept_timeout",
+    "active_external_ip",
+    "active_bind_address",
+    "passive_address",
+    "preserve_download_mtime",
+    "preserve_upload_mtime",
+    "host_name",
+];
+
+/// Config keys for `RAX_FTP_PROFILES_<NAME>_<KEY>` variables, written without `__`
+///
+/// The key is the longest profile setting the variable ends with, so
+/// `RAX_FTP_PROFILES_ACME_DATA_TIMEOUT` sets `profiles.acme.data_timeout`.
+/// Variables that name no setting are logged and skipped.
+fn profile_variables(
+    variables: impl IntoIterator<Item = (String, String)>,
+) -> Vec<(String, String)> {
+    let mut overrides = Vec::new();
+    for (variable, value) in variables {
+        let Some(rest) = variable.strip_prefix("RAX_FTP_PROFILES_") else {
+            continue;
+        };
+        // The `__` form is read by `environment()`
+        if rest.starts_with('_') {
+            continue;
+        }
+
+        let rest = rest.to_lowercase();
+        let profile = PROFILE_KEYS
+            .iter()
+            .filter_map(|key| {
+                let name = rest.strip_suffix(key)?.strip_suffix('_')?;
+                (!name.is_empty()).then_some((name, key))
+            })
+            .max_by_key(|(_, key)| key.len());
+        match profile {
+            Some((name, key)) => overrides.push((format!("profiles.{name}.{key}"), value)),
+            None => warn!("Ignoring {variable}: it names no profile setting"),
+        }
+    }
+    overrides
+}
+
 impl Default for ClientConfig {
     fn default() -> Self {
         Self {
@@ -140,10 +728,30 @@ impl Default for ClientConfig {
             port: 2121,
             timeout: 5,
             max_retries: 3,
+            retry: RetryConfig::default(),
+            proxy: ProxyConfig::default(),
+            ip_version: IpVersion::Any,
+            gateway_login: GatewayLogin::None,
+            gateway_target: None,
+            gateway_user: None,
+            gateway_password: None,
             local_directory: "./client_root".to_string(),
             data_port_start: 2122,
             data_port_end: 2130,
+            data_mode: DataMode::Passive,
+            data_timeout: default_data_timeout(),
+            data_accept_timeout: default_data_accept_timeout(),
+            active_external_ip: None,
+            active_bind_address: None,
+            passive_address: PassiveAddress::Auto,
+            preserve_download_mtime: true,
+            preserve_upload_mtime: true,
+            progress: ProgressStyle::Auto,
+            output: OutputFormat::Text,
+            netrc: NetrcConfig::default(),
             host_name: None,
+            profiles: BTreeMap::new(),
+            profile_name: None,
         }
     }
 }
@@ -163,3 +771,122 @@ impl std::fmt::Display for ClientConfig {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::FileFormat;
+
+    #[test]
+    fn test_profile_inherits_unset_values() {
+        let mut config = ClientConfig::default();
+        config.profiles.insert(
+            "acme".to_string(),
+            ProfileConfig {
+                host: Some("ftp.acme.example".to_string()),
+                data_mode: Some(DataMode::Active),
+                ..ProfileConfig::default()
+            },
+        );
+
+        let profile = config.profile("acme").unwrap();
+        assert_eq!(profile.host, "ftp.acme.example");
+        assert_eq!(profile.data_mode, DataMode::Active);
+        assert_eq!(profile.port, config.port);
+        assert_eq!(profile.display_name(), "acme");
+        assert_eq!(profile.profile_name.as_deref(), Some("acme"));
+        assert!(config.profile("other").is_none());
+    }
+
+    #[test]
+    fn test_login_host_uses_gateway_target() {
+        let mut config = ClientConfig {
+            host: "gateway.example".to_string(),
+            gateway_target: Some("ftp.partner.example:2121".to_string()),
+            ..ClientConfig::default()
+        };
+        assert_eq!(config.login_host(), "gateway.example");
+
+        config.gateway_login = GatewayLogin::Site;
+        assert_eq!(config.login_host(), "ftp.partner.example");
+        config.gateway_target = Some("[2001:db8::5]:21".to_string());
+        assert_eq!(config.login_host(), "2001:db8::5");
+    }
+
+    #[test]
+    fn test_environment_overrides_keys_with_underscores() {
+        let variables = [
+            ("RAX_FTP_DATA_MODE", "active"),
+            ("RAX_FTP_RETRY__MAX_DELAY_MS", "5000"),
+            ("RAX_FTP_PROFILES__ACME__HOST", "ftp.acme.example"),
+            ("RAX_FTP_PROFILES__ACME__DATA_MODE", "passive"),
+            ("RAX_FTP_PROFILES__ACME__LOCAL_DIRECTORY", "/srv/acme"),
+        ];
+        let environment = environment().source(Some(
+            variables
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        ));
+
+        let config: ClientConfig = Config::builder()
+            .add_source(File::from_str(
+                include_str!("../config.toml"),
+                FileFormat::Toml,
+            ))
+            .add_source(environment)
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert_eq!(config.data_mode, DataMode::Active);
+        assert_eq!(config.retry.max_delay_ms, 5000);
+        let acme = config.profile("acme").unwrap();
+        assert_eq!(acme.host, "ftp.acme.example");
+        assert_eq!(acme.data_mode, DataMode::Passive);
+        assert_eq!(acme.local_directory, "/srv/acme");
+    }
+
+    #[test]
+    fn test_profile_variables_without_double_underscores() {
+        let variables = [
+            ("RAX_FTP_PROFILES_ACME_HOST", "ftp.acme.example"),
+            ("RAX_FTP_PROFILES_ACME_DATA_TIMEOUT", "90"),
+            ("RAX_FTP_PROFILES_EU_PARTNER_PORT", "2100"),
+            ("RAX_FTP_PROFILES_ACME_COLOUR", "blue"),
+            ("RAX_FTP_PROFILES__ACME__PORT", "21"),
+            ("RAX_FTP_HOST", "127.0.0.1"),
+        ];
+        let overrides = profile_variables(
+            variables
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+        assert_eq!(
+            overrides,
+            [
+                (
+                    "profiles.acme.host".to_string(),
+                    "ftp.acme.example".to_string()
+                ),
+                ("profiles.acme.data_timeout".to_string(), "90".to_string()),
+                ("profiles.eu_partner.port".to_string(), "2100".to_string()),
+            ]
+        );
+
+        let mut builder = Config::builder().add_source(File::from_str(
+            include_str!("../config.toml"),
+            FileFormat::Toml,
+        ));
+        for (key, value) in overrides {
+            builder = builder.set_override(key, value).unwrap();
+        }
+        let config: ClientConfig = builder.build().unwrap().try_deserialize().unwrap();
+
+        let acme = config.profile("acme").unwrap();
+        assert_eq!(acme.host, "ftp.acme.example");
+        assert_eq!(acme.data_timeout, 90);
+        assert_eq!(config.profile("eu_partner").unwrap().port, 2100);
+    }
+}
@@ -3,20 +3,43 @@
 //! Handles TCP connection for FTP command channel (port 2121).
 
 use log::{debug, error, info, warn};
-use std::io::{self, BufRead, BufReader, Write};
-use std::net::{TcpStream, ToSocketAddrs};
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
 use std::time::Duration;
 
-use crate::config::ClientConfig;
+use super::happy_eyeballs;
+use super::proxy::Proxy;
+use super::reply::ReplyReader;
+use super::retry::RetryPolicy;
+use crate::commands::redact_secrets;
+use crate::config::{ClientConfig, IpVersion};
 use crate::error::{RaxFtpClientError, Result};
+use crate::responses::FtpResponse;
+
+/// Both ends of the control connection, used to choose data connection addresses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControlAddresses {
+    /// Our address, advertised in active mode unless overridden
+    pub local_ip: IpAddr,
+    /// The server's address, the only peer allowed to open an active data connection
+    ///
+    /// Unspecified when unknown: behind a proxy, unless the host is an IP address.
+    pub peer_ip: IpAddr,
+}
 
 /// Manages the FTP command connection (main control channel)
 pub struct CommandConnection {
     stream: Option<TcpStream>,
+    /// Reader over a clone of `stream`, kept for the whole connection
+    reader: Option<ReplyReader<TcpStream>>,
     host: String,
     port: u16,
     timeout: u64,
-    max_retries: u32,
+    retry: RetryPolicy,
+    ip_version: IpVersion,
+    proxy: Option<Proxy>,
+    /// Server IP of the current connection, if known
+    server_ip: Option<IpAddr>,
 }
 
 impl CommandConnection {
@@ -29,18 +52,22 @@ impl CommandConnection {
 
         Self {
             stream: None,
+            reader: None,
             host: config.host.clone(),
             port: config.port,
             timeout: config.timeout,
-            max_retries: config.max_retries,
+            retry: RetryPolicy::new(config),
+            ip_version: config.ip_version,
+            proxy: Proxy::from_config(&config.proxy),
+            server_ip: None,
         }
     }
 
     /// Connect to the FTP server with retry logic
-    pub fn connect_with_retries(&mut self) -> Result<String> {
+    pub fn connect_with_retries(&mut self) -> Result<FtpResponse> {
         info!("Attempting to connect to {}:{}", self.host, self.port);
 
-        for attempt in 1..=self.max_retries {
+        for attempt in 1..=self.retry.max_attempts {
             match self.connect() {
                 Ok(greeting) => {
                     info!("Successfully connected to FTP server on attempt {attempt}");
@@ -49,10 +76,10 @@ impl CommandConnection {
                 Err(e) => {
                     error!("Connection attempt {attempt} failed: {e}");
 
-                    if attempt < self.max_retries {
-                        let wait_time = attempt as u64; // Simple backoff: 1s, 2s, 3s
-                        warn!("Retrying in {wait_time} seconds...");
-                        std::thread::sleep(Duration::from_secs(wait_time));
+                    if self.retry.can_retry(attempt) {
+                        let delay = self.retry.delay(attempt);
+                        warn!("Retrying connection in {delay:.1?} after: {e}");
+                        std::thread::sleep(delay);
                     }
                 }
             }
@@ -60,52 +87,99 @@ impl CommandConnection {
 
         Err(RaxFtpClientError::ConnectionTimeout(format!(
             "Failed to connect after {} attempts",
-            self.max_retries
+            self.retry.max_attempts
         )))
     }
 
-    /// Connect to the FTP server (single attempt)
-    fn connect(&mut self) -> Result<String> {
-        // Resolve hostname to SocketAddr
-        let addr = format!("{}:{}", self.host, self.port)
-            .to_socket_addrs()
-            .map_err(|_| RaxFtpClientError::InvalidHost("Could not resolve host".to_string()))?
-            .next()
-            .ok_or_else(|| {
-                RaxFtpClientError::InvalidHost("No addresses found for host".to_string())
-            })?;
-
-        // Create TCP connection with timeout
-        let stream =
-            TcpStream::connect_timeout(&addr, Duration::from_secs(self.timeout)).map_err(|e| {
-                match e.kind() {
-                    io::ErrorKind::TimedOut => {
-                        RaxFtpClientError::ConnectionTimeout("Connection timed out".to_string())
-                    }
-                    io::ErrorKind::ConnectionRefused => RaxFtpClientError::ConnectionRefused(
-                        format!("Connection refused to {}:{}", self.host, self.port),
-                    ),
-                    _ => RaxFtpClientError::Io(e),
-                }
-            })?;
+    /// Connect to the FTP server (single attempt, directly or through the proxy)
+    fn connect(&mut self) -> Result<FtpResponse> {
+        let timeout = Duration::from_secs(self.timeout);
+        let (stream, server_ip) = match &self.proxy {
+            // The proxy resolves the host; its IP is only known if given literally
+            Some(proxy) => (
+                proxy.connect(&self.host, self.port, timeout)?,
+                self.host.parse().ok(),
+            ),
+            None => {
+                let (stream, addr) = self.connect_direct(timeout)?;
+                (stream, Some(addr.ip()))
+            }
+        };
 
-        // Rest of method unchanged...
         stream
-            .set_read_timeout(Some(Duration::from_secs(self.timeout)))
+            .set_read_timeout(Some(timeout))
             .map_err(RaxFtpClientError::Io)?;
         stream
-            .set_write_timeout(Some(Duration::from_secs(self.timeout)))
+            .set_write_timeout(Some(timeout))
             .map_err(RaxFtpClientError::Io)?;
 
+        self.reader = Some(ReplyReader::new(stream.try_clone()?));
         self.stream = Some(stream);
-        info!("Connected to FTP server at {}:{}", self.host, self.port);
+        self.server_ip = server_ip;
+        match &self.proxy {
+            Some(proxy) => info!(
+                "Connected to FTP server at {}:{} through {proxy}",
+                self.host, self.port
+            ),
+            None => info!("Connected to FTP server at {}:{}", self.host, self.port),
+        }
 
         let greeting = self.read_response()?;
-        info!("Server greeting: {}", greeting.trim());
+        info!("Server greeting: {greeting}");
 
         Ok(greeting)
     }
 
+    /// Connect straight to the server, racing every resolved address
+    fn connect_direct(&self, timeout: Duration) -> Result<(TcpStream, SocketAddr)> {
+        // Resolve hostname to every SocketAddr of the allowed IP versions
+        let addrs: Vec<SocketAddr> = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .map_err(|_| RaxFtpClientError::InvalidHost("Could not resolve host".to_string()))?
+            .filter(|addr| self.ip_version.allows(addr.ip()))
+            .collect();
+        if addrs.is_empty() {
+            return Err(RaxFtpClientError::InvalidHost(format!(
+                "No {} addresses found for host",
+                self.ip_version
+            )));
+        }
+
+        // Race the addresses, keeping the first that connects
+        let (stream, addr) =
+            happy_eyeballs::connect(&addrs, timeout).map_err(|e| match e.kind() {
+                io::ErrorKind::TimedOut => {
+                    RaxFtpClientError::ConnectionTimeout("Connection timed out".to_string())
+                }
+                io::ErrorKind::ConnectionRefused => RaxFtpClientError::ConnectionRefused(format!(
+                    "Connection refused to {}:{}",
+                    self.host, self.port
+                )),
+                _ => RaxFtpClientError::Io(e),
+            })?;
+        info!("Connected to {addr}");
+
+        Ok((stream, addr))
+    }
+
+    /// Local and server addresses of the control connection
+    pub fn addresses(&self) -> Result<ControlAddresses> {
+        let stream = self
+            .stream
+            .as_ref()
+            .ok_or_else(|| RaxFtpClientError::NotConnected("Not connected".to_string()))?;
+        Ok(ControlAddresses {
+            local_ip: stream.local_addr()?.ip(),
+            peer_ip: self.server_ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        })
+    }
+
+    /// Server address the control connection is connected to, if known
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.as_ref()?;
+        Some(SocketAddr::new(self.server_ip?, self.port))
+    }
+
     /// Check if the connection is active
     pub fn is_connected(&self) -> bool {
         self.stream.is_some()
@@ -135,6 +209,7 @@ impl CommandConnection {
                 match e.kind() {
                     io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionAborted => {
                         self.stream = None; // Now we can safely modify self.stream
+                        self.reader = None;
                         Err(RaxFtpClientError::ConnectionLost(
                             "Connection lost while sending".to_string(),
                         ))
@@ -145,38 +220,6 @@ impl CommandConnection {
         }
     }
 
-    /// Read a line from the command connection (for FTP responses)
-    pub fn read_line(&mut self) -> Result<String> {
-        if self.stream.is_none() {
-            return Err(RaxFtpClientError::NotConnected("Not connected".to_string()));
-        }
-
-        let result = {
-            let stream = self.stream.as_mut().unwrap();
-            let mut reader = BufReader::new(stream);
-            let mut line = String::new();
-            reader.read_line(&mut line).map(|_| line)
-        };
-
-        match result {
-            Ok(line) => {
-                debug!("Read line: {}", line.trim());
-                Ok(line)
-            }
-            Err(e) => {
-                match e.kind() {
-                    io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionAborted => {
-                        self.stream = None; // Now we can safely modify self.stream
-                        Err(RaxFtpClientError::ConnectionLost(
-                            "Connection lost while reading".to_string(),
-                        ))
-                    }
-                    _ => Err(RaxFtpClientError::Io(e)),
-                }
-            }
-        }
-    }
-
     /// Send an FTP command (adds CRLF automatically)
     pub fn send_command(&mut self, command: &str) -> Result<()> {
         let formatted_command = if command.ends_with("\r\n") {
@@ -185,49 +228,43 @@ impl CommandConnection {
             format!("{command}\r\n")
         };
 
-        debug!("Sending command: {command}");
+        debug!("Sending command: {}", redact_secrets(command));
         self.send_bytes(formatted_command.as_bytes())
     }
 
     /// Read an FTP response (handles multi-line responses)
-    pub fn read_response(&mut self) -> Result<String> {
-        let mut response = String::new();
-        let mut first_line = true;
-        let mut expected_code = None;
-
-        loop {
-            let line = self.read_line()?;
-            response.push_str(&line);
-
-            if first_line {
-                // Parse the response code from first line
-                if line.len() >= 4 {
-                    let code_part = &line[0..3];
-                    let separator = line.chars().nth(3).unwrap_or(' ');
-
-                    if separator == ' ' {
-                        // Single line response
-                        break;
-                    } else if separator == '-' {
-                        // Multi-line response
-                        expected_code = Some(code_part.to_string());
-                    }
-                }
-                first_line = false;
-            } else if let Some(ref code) = expected_code {
-                // Check if this is the end of multi-line response
-                if line.len() >= 4 && line.starts_with(code) && line.chars().nth(3) == Some(' ') {
-                    break;
-                }
+    pub fn read_response(&mut self) -> Result<FtpResponse> {
+        let reader = self
+            .reader
+            .as_mut()
+            .ok_or_else(|| RaxFtpClientError::NotConnected("Not connected".to_string()))?;
+
+        match reader.read_reply() {
+            Ok(response) => {
+                debug!("Received response: {response}");
+                Ok(response)
+            }
+            Err(RaxFtpClientError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::UnexpectedEof
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::ConnectionReset
+                ) =>
+            {
+                self.stream = None;
+                self.reader = None;
+                Err(RaxFtpClientError::ConnectionLost(
+                    "Connection lost while reading".to_string(),
+                ))
             }
+            Err(e) => Err(e),
         }
-
-        debug!("Received response: {}", response.trim());
-        Ok(response)
     }
 
     /// Disconnect from the server
     pub fn disconnect(&mut self) -> Result<()> {
+        self.reader = None;
         if let Some(stream) = self.stream.take() {
             info!("Disconnecting from FTP server");
             stream
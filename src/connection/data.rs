@@ -1,21 +1,29 @@
 //! Data connection management for FTP transfers
 
 use log::{debug, error, info, warn};
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::time::Duration;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
 
+use super::ControlAddresses;
+use super::proxy::Proxy;
+use crate::config::{ClientConfig, PassiveAddress};
 use crate::error::{RaxFtpClientError, Result};
 
 // Constants
-const DEFAULT_BIND_IP: &str = "0.0.0.0";
-const DEFAULT_TIMEOUT_SECS: u64 = 30;
-const DEFAULT_ACTIVE_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_BIND_IP: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+/// How often a non-blocking accept is retried while waiting for the server
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Manages FTP data connections for file transfers
 pub struct DataConnection {
     mode: DataConnectionMode,
+    /// Passive connect or active accept timeout
     timeout: Duration,
+    /// Read and write timeout on the established data stream
+    io_timeout: Duration,
+    /// Proxy passive connections are tunnelled through
+    proxy: Option<Proxy>,
 }
 
 /// Data connection mode
@@ -24,83 +32,125 @@ enum DataConnectionMode {
     Active {
         listener: Option<TcpListener>,
         stream: Option<TcpStream>,
+        /// Only the control connection's peer may connect
+        server_ip: IpAddr,
+        /// Address sent to the server with PORT
+        advertised: SocketAddr,
     },
     Passive {
         stream: Option<TcpStream>,
-        server_host: String,
-        server_port: u16,
+        server_addr: SocketAddr,
     },
 }
 
 impl DataConnection {
     /// Create a new data connection for PORT mode (Active) on a specific port
-    pub fn active_mode(port: u16) -> Result<Self> {
-        let addr = format!("{DEFAULT_BIND_IP}:{port}");
+    ///
+    /// Listens on `active_bind_address` (all interfaces by default) and
+    /// advertises `active_external_ip`, falling back to the control connection's
+    /// local address. Only connections from the control connection's peer are accepted.
+    pub fn active_mode(
+        port: u16,
+        control: ControlAddresses,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        let bind_ip = config.active_bind_address.unwrap_or(DEFAULT_BIND_IP);
+        let addr = SocketAddr::new(bind_ip, port);
 
-        let listener = TcpListener::bind(&addr).map_err(|e| {
+        let listener = TcpListener::bind(addr).map_err(|e| {
             warn!("Failed to bind to specific port {port}: {e}");
             RaxFtpClientError::DataConnectionFailed(format!("Failed to bind to port {port}: {e}"))
         })?;
 
-        info!("Created data connection listener on specific port {port}");
+        info!("Created data connection listener on {addr}");
 
-        // Set listener to blocking mode
-        listener.set_nonblocking(false)?;
+        // Accept polls so the timeout can be enforced
+        listener.set_nonblocking(true)?;
 
         Ok(Self {
             mode: DataConnectionMode::Active {
                 listener: Some(listener),
                 stream: None,
+                server_ip: control.peer_ip,
+                advertised: SocketAddr::new(advertised_ip(control, config), port),
             },
-            timeout: Duration::from_secs(DEFAULT_ACTIVE_TIMEOUT_SECS),
+            timeout: Duration::from_secs(config.data_accept_timeout),
+            io_timeout: Duration::from_secs(config.data_timeout),
+            proxy: None,
         })
     }
 
     /// Create a new data connection for PASV mode (Passive)
-    pub fn passive_mode(server_host: &str, server_port: u16) -> Result<Self> {
-        info!("Creating passive data connection to {server_host}:{server_port}");
+    ///
+    /// `reported` is the address from the 227 reply; `passive_address` decides
+    /// whether its IP is used or replaced by the control connection's peer.
+    pub fn passive_mode(
+        reported: SocketAddr,
+        control: ControlAddresses,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        let server_addr = passive_target(reported, control.peer_ip, config.passive_address);
+        if server_addr != reported {
+            info!("Server reported {reported} for PASV, connecting to {server_addr} instead");
+        }
 
         let connection = Self {
             mode: DataConnectionMode::Passive {
                 stream: None,
-                server_host: server_host.to_string(),
-                server_port,
+                server_addr,
             },
-            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            timeout: Duration::from_secs(config.data_timeout),
+            io_timeout: Duration::from_secs(config.data_timeout),
+            proxy: Proxy::from_config(&config.proxy),
         };
 
-        info!("Passive data connection configured for {server_host}:{server_port}");
+        info!("Passive data connection configured for {server_addr}");
         Ok(connection)
     }
 
+    /// Address to send with PORT, for active connections
+    pub fn advertised_address(&self) -> Option<SocketAddr> {
+        match &self.mode {
+            DataConnectionMode::Active { advertised, .. } => Some(*advertised),
+            DataConnectionMode::Passive { .. } => None,
+        }
+    }
+
     /// Connect to server (handles both Active and Passive modes)
     pub fn connect_to_server(&mut self) -> Result<()> {
         match &mut self.mode {
             DataConnectionMode::Passive {
                 stream,
-                server_host,
-                server_port,
+                server_addr,
             } => {
-                info!("Passive mode: Connecting to server at {server_host}:{server_port}");
-
-                let server_addr = format!("{server_host}:{server_port}");
+                info!("Passive mode: Connecting to server at {server_addr}");
                 debug!(
                     "Attempting connection to {} with {}s timeout",
                     server_addr,
                     self.timeout.as_secs()
                 );
-                let parsed_addr = server_addr.parse().map_err(|_| {
-                    RaxFtpClientError::DataConnectionFailed("Invalid server address".to_string())
-                })?;
 
-                match TcpStream::connect_timeout(&parsed_addr, self.timeout) {
+                let connected = match &self.proxy {
+                    Some(proxy) => proxy
+                        .connect(
+                            &server_addr.ip().to_string(),
+                            server_addr.port(),
+                            self.timeout,
+                        )
+                        .map_err(|e| e.to_string()),
+                    None => TcpStream::connect_timeout(server_addr, self.timeout)
+                        .map_err(|e| e.to_string()),
+                };
+
+                match connected {
                     Ok(tcp_stream) => {
-                        info!("Successfully connected to server at {parsed_addr}");
+                        info!("Successfully connected to server at {server_addr}");
+                        set_io_timeouts(&tcp_stream, self.io_timeout)?;
                         *stream = Some(tcp_stream);
                         Ok(())
                     }
                     Err(e) => {
-                        warn!("Connection attempt failed to {parsed_addr}: {e}");
+                        warn!("Connection attempt failed to {server_addr}: {e}");
                         error!("Failed to establish passive data connection: {e}");
                         Err(RaxFtpClientError::DataConnectionFailed(format!(
                             "Failed to connect to server: {e}"
@@ -108,34 +158,28 @@ impl DataConnection {
                     }
                 }
             }
-            DataConnectionMode::Active { listener, stream } => {
+            DataConnectionMode::Active {
+                listener,
+                stream,
+                server_ip,
+                ..
+            } => {
                 info!("Active mode: Waiting for server connection");
-                if let Some(listener) = listener.as_ref() {
-                    debug!(
-                        "Waiting for server connection on listener (timeout: {}s)",
-                        self.timeout.as_secs()
-                    );
-                    // Accept incoming connection from server
-                    match listener.accept() {
-                        Ok((tcp_stream, server_addr)) => {
-                            info!("Server successfully connected from: {server_addr}");
-                            *stream = Some(tcp_stream);
-                            Ok(())
-                        }
-                        Err(e) => {
-                            warn!("Server connection attempt failed: {e}");
-                            error!("Failed to accept connection in active mode: {e}");
-                            Err(RaxFtpClientError::DataConnectionFailed(format!(
-                                "Failed to accept connection: {e}"
-                            )))
-                        }
-                    }
-                } else {
+                let Some(listener) = listener.as_ref() else {
                     error!("No listener available for active mode");
-                    Err(RaxFtpClientError::DataConnectionFailed(
+                    return Err(RaxFtpClientError::DataConnectionFailed(
                         "No listener available for active mode".to_string(),
-                    ))
-                }
+                    ));
+                };
+                debug!(
+                    "Waiting for server connection on listener (timeout: {}s)",
+                    self.timeout.as_secs()
+                );
+
+                let tcp_stream = accept_from(listener, *server_ip, self.timeout)?;
+                set_io_timeouts(&tcp_stream, self.io_timeout)?;
+                *stream = Some(tcp_stream);
+                Ok(())
             }
         }
     }
@@ -201,18 +245,12 @@ impl DataConnection {
     /// Clean up the current connection and prepare for next transfer
     pub fn reset_connection(&mut self) -> Result<()> {
         match &mut self.mode {
-            DataConnectionMode::Active { stream, listener } => {
-                // Close the data stream
+            DataConnectionMode::Active { stream, .. } => {
+                // Close the data stream; the listener stays open for the next transfer
                 if let Some(stream) = stream.take() {
                     stream.shutdown(std::net::Shutdown::Both)?;
                     info!("Active mode data stream closed");
                 }
-
-                // Set listener back to non-blocking for next accept
-                if let Some(listener) = listener.as_ref() {
-                    listener.set_nonblocking(false)?;
-                    info!("Active mode listener set to blocking for next transfer");
-                }
             }
             DataConnectionMode::Passive { stream, .. } => {
                 // Just close the data stream
@@ -236,3 +274,191 @@ impl DataConnection {
         Ok(())
     }
 }
+
+/// IP to advertise with PORT: the configured external IP, else a specific
+/// bind address, else the control connection's local address
+fn advertised_ip(control: ControlAddresses, config: &ClientConfig) -> IpAddr {
+    config
+        .active_external_ip
+        .or(config.active_bind_address.filter(|ip| !ip.is_unspecified()))
+        .unwrap_or(control.local_ip)
+}
+
+/// Address to connect to for a PASV reply of `reported`
+///
+/// In `auto` mode a private, loopback or unspecified reported IP is replaced by
+/// the control connection's peer, unless that peer is itself non-public (the
+/// client is then on the same network and the reported IP is reachable). An
+/// unspecified peer (unknown behind a proxy) never replaces the reported IP.
+pub fn passive_target(reported: SocketAddr, peer_ip: IpAddr, mode: PassiveAddress) -> SocketAddr {
+    let use_peer = !peer_ip.is_unspecified()
+        && match mode {
+            PassiveAddress::Server => false,
+            PassiveAddress::Control => true,
+            PassiveAddress::Auto => {
+                reported.ip().is_unspecified() || (!is_public(reported.ip()) && is_public(peer_ip))
+            }
+        };
+
+    if use_peer {
+        SocketAddr::new(peer_ip, reported.port())
+    } else {
+        reported
+    }
+}
+
+/// Whether an address is routable on the internet (not private, loopback, link-local, ...)
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64; // 100.64.0.0/10
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00; // fc00::/7
+                let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80; // fe80::/10
+                !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+            }
+        },
+    }
+}
+
+/// Wait up to `timeout` for a connection from `server_ip` on a non-blocking listener
+///
+/// Connections from any other address are closed, so a third party cannot
+/// steal the transfer by connecting to the advertised port first.
+fn accept_from(listener: &TcpListener, server_ip: IpAddr, timeout: Duration) -> Result<TcpStream> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match listener.accept() {
+            Ok((tcp_stream, peer)) if peer.ip() == server_ip => {
+                info!("Server successfully connected from: {peer}");
+                tcp_stream.set_nonblocking(false)?;
+                return Ok(tcp_stream);
+            }
+            Ok((_, peer)) => {
+                warn!("Rejected data connection from {peer}: expected {server_ip}");
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => {
+                error!("Failed to accept connection in active mode: {e}");
+                return Err(RaxFtpClientError::DataConnectionFailed(format!(
+                    "Failed to accept connection: {e}"
+                )));
+            }
+        }
+
+        if Instant::now() >= deadline {
+            error!("Server did not open the data connection in time");
+            return Err(RaxFtpClientError::DataConnectionFailed(format!(
+                "No data connection from {server_ip} within {}s",
+                timeout.as_secs()
+            )));
+        }
+        std::thread::sleep(ACCEPT_POLL_INTERVAL);
+    }
+}
+
+/// Apply the data read/write timeout so a stalled transfer fails instead of hanging
+fn set_io_timeouts(stream: &TcpStream, timeout: Duration) -> Result<()> {
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passive_target_replaces_private_address() {
+        let reported: SocketAddr = "10.0.0.5:50000".parse().unwrap();
+        let public_peer: IpAddr = "203.0.113.7".parse().unwrap();
+        let private_peer: IpAddr = "192.168.1.20".parse().unwrap();
+
+        assert_eq!(
+            passive_target(reported, public_peer, PassiveAddress::Auto),
+            "203.0.113.7:50000".parse().unwrap()
+        );
+        // Same private network: the reported address is reachable
+        assert_eq!(
+            passive_target(reported, private_peer, PassiveAddress::Auto),
+            reported
+        );
+        assert_eq!(
+            passive_target(reported, public_peer, PassiveAddress::Server),
+            reported
+        );
+        assert_eq!(
+            passive_target(
+                "198.51.100.1:40000".parse().unwrap(),
+                private_peer,
+                PassiveAddress::Control
+            ),
+            "192.168.1.20:40000".parse().unwrap()
+        );
+    }
+
+    fn listener() -> (TcpListener, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    #[test]
+    fn test_accept_times_out_without_a_connection() {
+        let (listener, _) = listener();
+        let started = Instant::now();
+
+        let result = accept_from(
+            &listener,
+            IpAddr::from([127, 0, 0, 1]),
+            Duration::from_millis(200),
+        );
+        assert!(matches!(
+            result,
+            Err(RaxFtpClientError::DataConnectionFailed(_))
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_accept_rejects_an_unexpected_peer() {
+        use std::io::Read;
+
+        let (listener, addr) = listener();
+        let mut intruder = TcpStream::connect(addr).unwrap();
+
+        // Only 127.0.0.2 may connect, so the connection from 127.0.0.1 is closed
+        let result = accept_from(
+            &listener,
+            IpAddr::from([127, 0, 0, 2]),
+            Duration::from_millis(200),
+        );
+        assert!(matches!(
+            result,
+            Err(RaxFtpClientError::DataConnectionFailed(_))
+        ));
+        intruder
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(intruder.read(&mut [0u8; 1]).unwrap(), 0);
+
+        let _server = TcpStream::connect(addr).unwrap();
+        let stream = accept_from(
+            &listener,
+            IpAddr::from([127, 0, 0, 1]),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert_eq!(stream.local_addr().unwrap(), addr);
+    }
+}
@@ -0,0 +1,117 @@
+//! Connecting to every resolved address of a host (RFC 8305 "Happy Eyeballs")
+//!
+//! Addresses are tried IPv6 first, alternating between families. A new attempt
+//! starts when the previous one fails or after a short stagger, whichever comes
+//! first, and the first connection to succeed is used.
+
+use log::debug;
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Delay before starting the next attempt while earlier ones are still pending
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connect to the first reachable address, returning the stream and the address used
+pub fn connect(addrs: &[SocketAddr], timeout: Duration) -> io::Result<(TcpStream, SocketAddr)> {
+    let addrs = interleave(addrs);
+    let deadline = Instant::now() + timeout;
+    let (sender, receiver) = mpsc::channel();
+
+    let mut next = 0;
+    let mut pending = 0;
+    let mut last_error = None;
+
+    loop {
+        if next < addrs.len() {
+            let addr = addrs[next];
+            let sender = sender.clone();
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            debug!("Connecting to {addr}");
+            thread::spawn(move || {
+                // Nobody is listening any more once another attempt has won
+                let _ = sender.send((addr, TcpStream::connect_timeout(&addr, remaining)));
+            });
+            next += 1;
+            pending += 1;
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let wait = if next < addrs.len() {
+            remaining.min(CONNECTION_ATTEMPT_DELAY)
+        } else {
+            remaining
+        };
+
+        match receiver.recv_timeout(wait) {
+            Ok((addr, Ok(stream))) => return Ok((stream, addr)),
+            Ok((addr, Err(e))) => {
+                debug!("Connection to {addr} failed: {e}");
+                pending -= 1;
+                last_error = Some(e);
+                if pending == 0 && next == addrs.len() {
+                    break;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) if remaining.is_zero() || wait == remaining => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Connection timed out",
+                ));
+            }
+            // Stagger elapsed: start the next attempt alongside the pending ones
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No addresses to connect to")))
+}
+
+/// Order addresses IPv6 first, alternating families, keeping the resolver's order within each
+fn interleave(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) =
+        addrs.iter().partition(|addr| addr.is_ipv6());
+    let mut v6 = v6.into_iter();
+    let mut v4 = v4.into_iter();
+
+    let mut ordered = Vec::with_capacity(addrs.len());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave_alternates_families() {
+        let addrs: Vec<SocketAddr> = [
+            "10.0.0.1:21",
+            "10.0.0.2:21",
+            "[2001:db8::1]:21",
+            "10.0.0.3:21",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+
+        let ordered: Vec<String> = interleave(&addrs).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            ordered,
+            [
+                "[2001:db8::1]:21",
+                "10.0.0.1:21",
+                "10.0.0.2:21",
+                "10.0.0.3:21"
+            ]
+        );
+    }
+}
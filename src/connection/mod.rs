@@ -4,7 +4,11 @@
 
 pub mod command;
 pub mod data;
+pub mod happy_eyeballs;
+pub mod proxy;
+pub mod reply;
+pub mod retry;
 
 // Re-export main types
-pub use command::CommandConnection;
+pub use command::{CommandConnection, ControlAddresses};
 pub use data::DataConnection;
@@ -0,0 +1,187 @@
+//! Control channel reply reader
+//!
+//! Keeps one buffered reader for the whole connection, so bytes the server
+//! sends ahead (e.g. "150" and "226" in one packet) wait for the next read
+//! instead of being dropped. Telnet commands (RFC 854) are removed and lines
+//! may end in CRLF or a bare LF. Lines that are not UTF-8 are read as
+//! Latin-1, so every byte, including an escaped 0xFF, comes through as is.
+
+use log::debug;
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::error::{RaxFtpClientError, Result};
+use crate::responses::{FtpResponse, split_reply_line};
+
+/// Telnet "Interpret As Command" escape byte
+const IAC: u8 = 255;
+/// Telnet option negotiation commands (WILL, WONT, DO, DONT) take one option byte
+const WILL: u8 = 251;
+const DONT: u8 = 254;
+
+/// Reads FTP replies from a control connection stream
+pub struct ReplyReader<R> {
+    reader: BufReader<R>,
+    /// Bytes of a line interrupted by a read error (e.g. a timeout)
+    partial: Vec<u8>,
+    /// Lines of a multi-line reply interrupted by a read error
+    pending: Vec<String>,
+}
+
+impl<R: Read> ReplyReader<R> {
+    /// Wrap the read half of a control connection
+    pub fn new(inner: R) -> Self {
+        Self {
+            reader: BufReader::new(inner),
+            partial: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Read one complete reply, following multi-line replies to their last line
+    ///
+    /// After a read error (e.g. a timeout) the lines read so far are kept, and
+    /// the next call carries on with the same reply.
+    pub fn read_reply(&mut self) -> Result<FtpResponse> {
+        if self.pending.is_empty() {
+            let first_line = self.read_line()?;
+            split_reply_line(&first_line).map_err(RaxFtpClientError::InvalidResponse)?;
+            self.pending.push(first_line);
+        }
+        let (code, separator, _) =
+            split_reply_line(&self.pending[0]).map_err(RaxFtpClientError::InvalidResponse)?;
+
+        // A multi-line reply ends with a line starting with the same code and a space;
+        // the first line ("211-...") never does
+        if separator == '-' {
+            let last_line_prefix = format!("{code} ");
+            while !self
+                .pending
+                .last()
+                .is_some_and(|line| line.starts_with(&last_line_prefix))
+            {
+                let line = self.read_line()?;
+                self.pending.push(line);
+            }
+        }
+
+        let lines = std::mem::take(&mut self.pending);
+        FtpResponse::from_lines(lines).map_err(RaxFtpClientError::InvalidResponse)
+    }
+
+    /// Read one line without its line ending and with Telnet commands removed
+    ///
+    /// End of stream is reported as `UnexpectedEof`.
+    pub fn read_line(&mut self) -> io::Result<String> {
+        loop {
+            match self.next_byte()? {
+                IAC => match self.next_byte()? {
+                    // Escaped 0xFF data byte
+                    IAC => self.partial.push(IAC),
+                    WILL..=DONT => {
+                        let option = self.next_byte()?;
+                        debug!("Ignoring Telnet negotiation {option}");
+                    }
+                    command => debug!("Ignoring Telnet command {command}"),
+                },
+                b'\n' => {
+                    let mut line = std::mem::take(&mut self.partial);
+                    if line.last() == Some(&b'\r') {
+                        line.pop();
+                    }
+                    let line = decode_line(line);
+                    debug!("Read line: {line}");
+                    return Ok(line);
+                }
+                byte => self.partial.push(byte),
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<u8> {
+        let byte = match self.reader.fill_buf()?.first() {
+            Some(&byte) => byte,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "control connection closed",
+                ));
+            }
+        };
+        self.reader.consume(1);
+        Ok(byte)
+    }
+}
+
+/// Decode a line as UTF-8, or byte for byte as Latin-1 when it is not valid UTF-8
+fn decode_line(line: Vec<u8>) -> String {
+    String::from_utf8(line).unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(script: &[u8]) -> ReplyReader<&[u8]> {
+        ReplyReader::new(script)
+    }
+
+    #[test]
+    fn test_pipelined_replies_are_kept() {
+        let mut replies = reader(b"150 Opening data connection\r\n226 Transfer complete\r\n");
+        assert_eq!(replies.read_reply().unwrap().code, 150);
+        assert_eq!(replies.read_reply().unwrap().code, 226);
+        assert!(matches!(
+            replies.read_reply(),
+            Err(RaxFtpClientError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn test_multiline_reply_with_bare_lf() {
+        let mut replies = reader(b"211-Features:\n MDTM\n 211 in body\n211 End\n200 OK\r\n");
+        let feat = replies.read_reply().unwrap();
+        assert!(feat.multiline);
+        assert_eq!(feat.body(), [" MDTM", " 211 in body"]);
+        assert_eq!(replies.read_reply().unwrap().code, 200);
+    }
+
+    #[test]
+    fn test_telnet_commands_are_removed() {
+        // IAC IP, IAC DM, IAC WILL <option> and an escaped 0xFF
+        let mut replies = reader(b"\xff\xf4\xff\xf2\xff\xfb\x01226 Abort \xff\xffok\r\n");
+        let reply = replies.read_reply().unwrap();
+        assert_eq!(reply.code, 226);
+        assert_eq!(reply.message, "Abort \u{ff}ok");
+    }
+
+    /// A stream that fails with a timeout between chunks of data
+    struct Interrupted(Vec<io::Result<&'static [u8]>>);
+
+    impl Read for Interrupted {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0)?;
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_timeout_inside_multiline_reply_keeps_lines() {
+        let mut replies = ReplyReader::new(Interrupted(vec![
+            Ok(b"211-Features:\r\n MDTM\r\n SI"),
+            Err(io::ErrorKind::TimedOut.into()),
+            Ok(b"ZE\r\n211 End\r\n"),
+        ]));
+        assert!(matches!(
+            replies.read_reply(),
+            Err(RaxFtpClientError::Io(e)) if e.kind() == io::ErrorKind::TimedOut
+        ));
+
+        let feat = replies.read_reply().unwrap();
+        assert_eq!(feat.message, "Features:");
+        assert_eq!(feat.body(), [" MDTM", " SIZE"]);
+    }
+}
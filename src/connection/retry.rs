@@ -0,0 +1,94 @@
+//! Retry policy: exponential backoff with jitter
+//!
+//! The delay before retry `n` is `base_delay * multiplier^(n - 1)`, capped at
+//! `max_delay`, then moved randomly by up to `jitter` of itself so clients
+//! that failed together do not retry together.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::config::ClientConfig;
+
+/// When and how often to retry a failed operation
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    base_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Build the policy from `max_retries` and the `[retry]` settings
+    pub fn new(config: &ClientConfig) -> Self {
+        Self {
+            max_attempts: config.max_retries,
+            base_delay: Duration::from_millis(config.retry.base_delay_ms),
+            multiplier: config.retry.multiplier,
+            max_delay: Duration::from_millis(config.retry.max_delay_ms),
+            jitter: config.retry.jitter,
+        }
+    }
+
+    /// Whether another attempt may follow the given (1-based) attempt
+    pub fn can_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// Delay to wait after the given (1-based) failed attempt
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.delay_with(attempt, random_unit())
+    }
+
+    /// Delay for a random value in `[0, 1)`; 0.5 gives the nominal delay
+    fn delay_with(&self, attempt: u32, random: f64) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let nominal = (self.base_delay.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+        let jittered = nominal * (1.0 + self.jitter * (2.0 * random - 1.0));
+        Duration::from_secs_f64(jittered.clamp(0.0, self.max_delay.as_secs_f64()))
+    }
+}
+
+/// A random value in `[0, 1)`, good enough to spread retries apart
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(5),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = policy(0.0);
+        let delays: Vec<u64> = (1..=4)
+            .map(|attempt| policy.delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 5]);
+        assert!(policy.can_retry(4));
+        assert!(!policy.can_retry(5));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = policy(0.5);
+        assert_eq!(policy.delay_with(2, 0.0), Duration::from_secs(1));
+        assert_eq!(policy.delay_with(2, 0.5), Duration::from_secs(2));
+        assert_eq!(policy.delay_with(3, 0.99), Duration::from_secs(5));
+    }
+}
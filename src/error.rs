@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::responses::FtpResponse;
+
 /// Main error type for the RAX FTP Client
 #[derive(Debug)]
 pub enum RaxFtpClientError {
@@ -9,6 +11,7 @@ pub enum RaxFtpClientError {
     ConnectionLost(String),
     NotConnected(String),
     InvalidHost(String),
+    ProxyFailed(String),
 
     // Authentication Errors
     NotAuthenticated(String),
@@ -19,6 +22,24 @@ pub enum RaxFtpClientError {
     DataConnectionFailed(String),
     PermissionDenied { code: u16, message: String },
 
+    // Protocol Errors
+    InvalidResponse(String),
+
+    // Command Errors
+    InvalidCommand(String),
+
+    // Server Reply Errors (negative replies, with the reply as sent)
+    ServiceNotAvailable(FtpResponse),
+    CannotOpenDataConnection(FtpResponse),
+    TransferAborted(FtpResponse),
+    FileBusy(FtpResponse),
+    ServerLocalError(FtpResponse),
+    InsufficientStorage(FtpResponse),
+    NotLoggedIn(FtpResponse),
+    FileUnavailable(FtpResponse),
+    FileNameNotAllowed(FtpResponse),
+    CommandFailed(FtpResponse),
+
     // Configuration Errors
     InvalidPort(String),
     InvalidConfigValue(String),
@@ -36,6 +57,7 @@ impl fmt::Display for RaxFtpClientError {
             Self::ConnectionLost(msg) => write!(f, "Connection lost: {msg}"),
             Self::NotConnected(msg) => write!(f, "Not connected: {msg}"),
             Self::InvalidHost(msg) => write!(f, "Invalid host: {msg}"),
+            Self::ProxyFailed(msg) => write!(f, "Proxy failed: {msg}"),
 
             // Authentication Errors
             Self::NotAuthenticated(msg) => write!(f, "{msg}"),
@@ -52,6 +74,26 @@ impl fmt::Display for RaxFtpClientError {
                 write!(f, "Permission denied ({code}): {message}")
             }
 
+            // Protocol Errors
+            Self::InvalidResponse(msg) => write!(f, "Invalid response: {msg}"),
+
+            // Command Errors
+            Self::InvalidCommand(msg) => write!(f, "{msg}"),
+
+            // Server Reply Errors
+            Self::ServiceNotAvailable(reply) => write_reply(f, "Service not available", reply),
+            Self::CannotOpenDataConnection(reply) => {
+                write_reply(f, "Cannot open data connection", reply)
+            }
+            Self::TransferAborted(reply) => write_reply(f, "Transfer aborted", reply),
+            Self::FileBusy(reply) => write_reply(f, "File busy", reply),
+            Self::ServerLocalError(reply) => write_reply(f, "Server error", reply),
+            Self::InsufficientStorage(reply) => write_reply(f, "Insufficient storage", reply),
+            Self::NotLoggedIn(reply) => write_reply(f, "Not logged in", reply),
+            Self::FileUnavailable(reply) => write_reply(f, "File unavailable", reply),
+            Self::FileNameNotAllowed(reply) => write_reply(f, "File name not allowed", reply),
+            Self::CommandFailed(reply) => write_reply(f, "Command failed", reply),
+
             // Configuration Errors
             Self::InvalidPort(msg) => write!(f, "Invalid port: {msg}"),
             Self::InvalidConfigValue(msg) => write!(f, "Invalid config value: {msg}"),
@@ -62,6 +104,100 @@ impl fmt::Display for RaxFtpClientError {
     }
 }
 
+impl RaxFtpClientError {
+    /// Stable machine-readable name of the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ConnectionRefused(_) => "connection_refused",
+            Self::ConnectionTimeout(_) => "connection_timeout",
+            Self::ConnectionLost(_) => "connection_lost",
+            Self::NotConnected(_) => "not_connected",
+            Self::InvalidHost(_) => "invalid_host",
+            Self::ProxyFailed(_) => "proxy_failed",
+            Self::NotAuthenticated(_) => "not_authenticated",
+            Self::FileNotFound { .. } => "file_not_found",
+            Self::TransferFailed { .. } => "transfer_failed",
+            Self::DataConnectionFailed(_) => "data_connection_failed",
+            Self::PermissionDenied { .. } => "permission_denied",
+            Self::InvalidResponse(_) => "invalid_response",
+            Self::InvalidCommand(_) => "invalid_command",
+            Self::ServiceNotAvailable(_) => "service_not_available",
+            Self::CannotOpenDataConnection(_) => "cannot_open_data_connection",
+            Self::TransferAborted(_) => "transfer_aborted",
+            Self::FileBusy(_) => "file_busy",
+            Self::ServerLocalError(_) => "server_local_error",
+            Self::InsufficientStorage(_) => "insufficient_storage",
+            Self::NotLoggedIn(_) => "not_logged_in",
+            Self::FileUnavailable(_) => "file_unavailable",
+            Self::FileNameNotAllowed(_) => "file_name_not_allowed",
+            Self::CommandFailed(_) => "command_failed",
+            Self::InvalidPort(_) => "invalid_port",
+            Self::InvalidConfigValue(_) => "invalid_config_value",
+            Self::Io(_) => "io",
+        }
+    }
+
+    /// Error for a negative (4xx/5xx) server reply
+    pub fn from_reply(reply: FtpResponse) -> Self {
+        match reply.code {
+            421 => Self::ServiceNotAvailable(reply),
+            425 => Self::CannotOpenDataConnection(reply),
+            426 => Self::TransferAborted(reply),
+            450 => Self::FileBusy(reply),
+            451 => Self::ServerLocalError(reply),
+            452 | 552 => Self::InsufficientStorage(reply),
+            530 => Self::NotLoggedIn(reply),
+            550 => Self::FileUnavailable(reply),
+            553 => Self::FileNameNotAllowed(reply),
+            _ => Self::CommandFailed(reply),
+        }
+    }
+
+    /// Server reply that caused the error, if it came from the server
+    pub fn reply(&self) -> Option<&FtpResponse> {
+        match self {
+            Self::ServiceNotAvailable(reply)
+            | Self::CannotOpenDataConnection(reply)
+            | Self::TransferAborted(reply)
+            | Self::FileBusy(reply)
+            | Self::ServerLocalError(reply)
+            | Self::InsufficientStorage(reply)
+            | Self::NotLoggedIn(reply)
+            | Self::FileUnavailable(reply)
+            | Self::FileNameNotAllowed(reply)
+            | Self::CommandFailed(reply) => Some(reply),
+            _ => None,
+        }
+    }
+
+    /// Server or client reply code carried by the error, if any
+    pub fn code(&self) -> Option<u16> {
+        match self {
+            Self::FileNotFound { code, .. }
+            | Self::TransferFailed { code, .. }
+            | Self::PermissionDenied { code, .. } => Some(*code),
+            _ => self.reply().map(|reply| reply.code),
+        }
+    }
+
+    /// Whether a failed transfer may succeed if tried again
+    /// (425, 426, 450, 451 or a broken data connection)
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::CannotOpenDataConnection(_)
+                | Self::TransferAborted(_)
+                | Self::FileBusy(_)
+                | Self::ServerLocalError(_)
+                | Self::DataConnectionFailed(_)
+        )
+    }
+}
+
+fn write_reply(f: &mut fmt::Formatter<'_>, label: &str, reply: &FtpResponse) -> fmt::Result {
+    write!(f, "{label} ({}): {}", reply.code, reply.message)
+}
+
 impl std::error::Error for RaxFtpClientError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -85,3 +221,46 @@ impl From<std::num::ParseIntError> for RaxFtpClientError {
 
 /// Result type alias for convenience
 pub type Result<T> = std::result::Result<T, RaxFtpClientError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(line: &str) -> FtpResponse {
+        FtpResponse::from_lines(vec![line.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_from_reply_keeps_reply() {
+        let error = RaxFtpClientError::from_reply(reply("452 Disk full"));
+        assert_eq!(error.kind(), "insufficient_storage");
+        assert_eq!(error.code(), Some(452));
+        assert_eq!(error.to_string(), "Insufficient storage (452): Disk full");
+
+        let error = RaxFtpClientError::from_reply(reply("550 No such file"));
+        assert_eq!(error.kind(), "file_unavailable");
+        assert_eq!(
+            error.reply().map(|reply| reply.message.as_str()),
+            Some("No such file")
+        );
+    }
+
+    #[test]
+    fn test_kind_of_client_side_errors() {
+        let error = RaxFtpClientError::FileNotFound {
+            code: 550,
+            message: "missing.txt".to_string(),
+        };
+        assert_eq!(error.kind(), "file_not_found");
+        assert_eq!(error.code(), Some(550));
+        assert!(error.reply().is_none());
+
+        let error = RaxFtpClientError::from(std::io::Error::other("broken pipe"));
+        assert_eq!(error.kind(), "io");
+        assert_eq!(error.code(), None);
+        assert_eq!(
+            RaxFtpClientError::ConnectionTimeout("slow".to_string()).kind(),
+            "connection_timeout"
+        );
+    }
+}
@@ -1,36 +1,151 @@
+use clap::Parser;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
 use std::process;
 
+mod cli;
 mod client;
 mod commands;
 mod config;
 mod connection;
 mod error;
+mod netrc;
 mod responses;
 mod terminal;
 mod transfer;
+mod url;
 
+use cli::Cli;
 use client::RaxFtpClient;
-use config::ClientConfig;
+use config::{ClientConfig, OutputFormat};
+use terminal::batch::ExitStatus;
+use terminal::oneshot::plan_steps;
 use terminal::session::Terminal;
+use url::{FtpUrl, Scheme};
 
 fn main() {
     // Initialize logging
     env_logger::init();
 
+    let cli = Cli::parse();
+    if cli.command.is_some() && cli.batch.is_some() {
+        eprintln!("A one-shot command cannot be combined with --batch");
+        process::exit(ExitStatus::Usage.code());
+    }
+
     // Parse configuration from TOML file with environment variable overrides
-    let config = match ClientConfig::load() {
+    let mut config = match ClientConfig::load() {
         Ok(config) => config,
         Err(e) => {
             eprintln!("Failed to load client configuration: {e}");
             process::exit(1);
         }
     };
+
+    // Other profiles and servers opened later start from the top-level settings
+    let mut defaults = config.clone();
+
+    // A named profile replaces the top-level server settings
+    if let Some(name) = &cli.profile {
+        config = config.with_profile(name).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(ExitStatus::Usage.code());
+        });
+    }
+
+    // One-shot commands take the server and credentials from their URL
+    let one_shot = cli.command.as_ref().map(|command| {
+        let url = FtpUrl::parse(command.url()).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(ExitStatus::Usage.code());
+        });
+        if url.scheme == Scheme::Ftps {
+            eprintln!("ftps:// URLs are not supported: this client has no TLS support");
+            process::exit(ExitStatus::Usage.code());
+        }
+
+        config.host = url.host.clone();
+        config.port = url.port_or_default();
+        config.host_name = None;
+        // Credentials in the URL take precedence over netrc
+        if url.user.is_some() {
+            config.netrc.enabled = false;
+        }
+        (command, url)
+    });
+
+    // Command-line flags take precedence over the config file and URL
+    cli.apply_overrides(&mut config);
+    cli.apply_overrides(&mut defaults);
+
+    // Machine-readable output for automation
+    if cli.json {
+        config.output = OutputFormat::Json;
+        defaults.output = OutputFormat::Json;
+    }
+
+    let one_shot_steps = one_shot.map(|(command, url)| {
+        // Without URL credentials, a netrc entry replaces the anonymous login
+        let netrc_login = netrc::entry_for(&config)
+            .unwrap_or_else(|e| {
+                eprintln!("Ignoring netrc: {e}");
+                None
+            })
+            .is_some_and(|entry| entry.login.is_some());
+
+        plan_steps(
+            command,
+            &url,
+            Path::new(&config.local_directory),
+            netrc_login,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(ExitStatus::Usage.code());
+        })
+    });
     // Create client (starts in disconnected state)
-    let client = RaxFtpClient::new(config.clone());
+    let client = RaxFtpClient::with_defaults(config.clone(), defaults);
 
-    // Create terminal and run interactive session
-    // Terminal will handle the connection attempt internally
     let mut terminal = Terminal::new(client, config);
+
+    // One-shot mode: run a single operation and exit
+    if let Some(steps) = one_shot_steps {
+        match terminal.run_steps(steps) {
+            Ok(status) => process::exit(status.code()),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                process::exit(ExitStatus::from_error(&e).code());
+            }
+        }
+    }
+
+    // Batch mode: run a script and report the outcome through the exit code
+    if let Some(script_path) = cli.batch {
+        let result = if script_path.as_os_str() == "-" {
+            terminal.run_batch(io::stdin().lock(), cli.continue_on_error)
+        } else {
+            match File::open(&script_path) {
+                Ok(file) => terminal.run_batch(BufReader::new(file), cli.continue_on_error),
+                Err(e) => {
+                    eprintln!("Cannot open script '{}': {e}", script_path.display());
+                    process::exit(ExitStatus::Usage.code());
+                }
+            }
+        };
+
+        match result {
+            Ok(status) => process::exit(status.code()),
+            Err(e) => {
+                eprintln!("Batch error: {e}");
+                process::exit(ExitStatus::from_error(&e).code());
+            }
+        }
+    }
+
+    // Run interactive session
+    // Terminal will handle the connection attempt internally
     if let Err(e) = terminal.run_interactive() {
         eprintln!("Terminal error: {e}");
         process::exit(1);
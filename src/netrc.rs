@@ -0,0 +1,225 @@
+//! ~/.netrc parsing for automatic login
+//!
+//! Supports `machine`, `default`, `login`, `password`, `account` and
+//! `macdef` entries. Macro bodies run until the next blank line.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::ClientConfig;
+use crate::error::{RaxFtpClientError, Result};
+
+/// Name of the macro run right after an automatic login
+pub const INIT_MACRO: &str = "init";
+
+/// Credentials and macros for one `machine` (or the `default`) entry
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetrcEntry {
+    /// Host name, or `None` for the `default` entry
+    pub machine: Option<String>,
+    pub login: Option<String>,
+    pub password: Option<String>,
+    pub account: Option<String>,
+    /// Macro name -> command lines
+    pub macros: BTreeMap<String, Vec<String>>,
+}
+
+/// Parsed netrc file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Netrc {
+    pub entries: Vec<NetrcEntry>,
+}
+
+impl Netrc {
+    /// Parse netrc contents
+    pub fn parse(contents: &str) -> Self {
+        let mut netrc = Netrc::default();
+        let mut tokens = Tokens::new(contents);
+
+        while let Some(token) = tokens.next_token() {
+            match token {
+                "machine" => netrc.entries.push(NetrcEntry {
+                    machine: tokens.next_token().map(str::to_string),
+                    ..NetrcEntry::default()
+                }),
+                "default" => netrc.entries.push(NetrcEntry::default()),
+                "login" | "password" | "account" => {
+                    let value = tokens.next_token().map(str::to_string);
+                    // Values before any machine entry have nothing to attach to
+                    let Some(entry) = netrc.entries.last_mut() else {
+                        continue;
+                    };
+                    match token {
+                        "login" => entry.login = value,
+                        "password" => entry.password = value,
+                        _ => entry.account = value,
+                    }
+                }
+                "macdef" => {
+                    let name = tokens.next_token().unwrap_or_default().to_string();
+                    let body = tokens.macro_body();
+                    if let Some(entry) = netrc.entries.last_mut() {
+                        entry.macros.insert(name, body);
+                    }
+                }
+                // Unknown keywords (e.g. "port") are ignored
+                _ => {}
+            }
+        }
+
+        netrc
+    }
+
+    /// Read and parse a netrc file, returning `None` if it does not exist
+    ///
+    /// Like classic ftp, files that group or others can access are refused
+    /// since they may hold passwords.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = metadata.permissions().mode();
+            if mode & 0o077 != 0 {
+                return Err(RaxFtpClientError::InvalidConfigValue(format!(
+                    "{} is accessible by group or others (mode {:o}); run 'chmod 600 {}'",
+                    path.display(),
+                    mode & 0o777,
+                    path.display()
+                )));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Some(Self::parse(&contents)))
+    }
+
+    /// Entry for `host`, falling back to the `default` entry
+    pub fn find(&self, host: &str) -> Option<&NetrcEntry> {
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry
+                    .machine
+                    .as_deref()
+                    .is_some_and(|machine| machine.eq_ignore_ascii_case(host))
+            })
+            .or_else(|| self.entries.iter().find(|entry| entry.machine.is_none()))
+    }
+}
+
+/// Default netrc location (`~/.netrc`)
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".netrc"))
+}
+
+/// Netrc entry for the configured host (the gateway's target, if any), if netrc login is enabled
+pub fn entry_for(config: &ClientConfig) -> Result<Option<NetrcEntry>> {
+    if !config.netrc.enabled {
+        return Ok(None);
+    }
+
+    let path = match &config.netrc.path {
+        Some(path) => PathBuf::from(path),
+        None => match default_path() {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Netrc::load(&path)?.and_then(|netrc| netrc.find(config.login_host()).cloned()))
+}
+
+/// Whitespace-separated tokens that can also hand out raw lines for macro bodies
+struct Tokens<'a> {
+    lines: std::str::Lines<'a>,
+    current: std::str::SplitWhitespace<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(contents: &'a str) -> Self {
+        Self {
+            lines: contents.lines(),
+            current: "".split_whitespace(),
+        }
+    }
+
+    fn next_token(&mut self) -> Option<&'a str> {
+        loop {
+            if let Some(token) = self.current.next() {
+                return Some(token);
+            }
+            let line = self.lines.next()?;
+            if !line.trim_start().starts_with('#') {
+                self.current = line.split_whitespace();
+            }
+        }
+    }
+
+    /// Lines following the `macdef` line, up to the next blank line
+    fn macro_body(&mut self) -> Vec<String> {
+        self.current = "".split_whitespace();
+        self.lines
+            .by_ref()
+            .take_while(|line| !line.trim().is_empty())
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# partner servers
+machine ftp.acme.example login alice password s3cret
+machine files.example.com
+    login bob
+    password hunter2
+    account billing
+macdef init
+CWD /incoming
+PWD
+
+default login anonymous password guest@
+";
+
+    #[test]
+    fn test_parse_entries_and_macros() {
+        let netrc = Netrc::parse(SAMPLE);
+        assert_eq!(netrc.entries.len(), 3);
+
+        let bob = &netrc.entries[1];
+        assert_eq!(bob.machine.as_deref(), Some("files.example.com"));
+        assert_eq!(bob.login.as_deref(), Some("bob"));
+        assert_eq!(bob.account.as_deref(), Some("billing"));
+        assert_eq!(bob.macros[INIT_MACRO], vec!["CWD /incoming", "PWD"]);
+
+        // Tokens after the macro body belong to the next entry
+        assert_eq!(netrc.entries[2].machine, None);
+        assert_eq!(netrc.entries[2].password.as_deref(), Some("guest@"));
+    }
+
+    #[test]
+    fn test_find_falls_back_to_default() {
+        let netrc = Netrc::parse(SAMPLE);
+        assert_eq!(
+            netrc.find("FTP.ACME.EXAMPLE").unwrap().login.as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            netrc.find("other.example").unwrap().login.as_deref(),
+            Some("anonymous")
+        );
+        assert_eq!(Netrc::parse("machine a login b").find("c"), None);
+    }
+}
@@ -4,5 +4,7 @@ pub mod parser;
 pub mod status_codes;
 
 // Re-export main types
-pub use parser::{FtpResponse, parse_response};
+pub use parser::{
+    FtpResponse, format_port_argument, parse_pasv_response, parse_pwd_response, split_reply_line,
+};
 pub use status_codes::*;
@@ -1,6 +1,7 @@
 //! FTP response parsing functionality
 
 use log::debug;
+use std::net::{Ipv4Addr, SocketAddr};
 
 /// Parsed FTP response from server
 #[derive(Debug, Clone, PartialEq)]
@@ -8,56 +9,264 @@ pub struct FtpResponse {
     /// Response code (e.g., 230, 530, 331)
     pub code: u16,
 
-    /// Response message (e.g., "User logged in, proceed")
+    /// Response message from the first line (e.g., "User logged in, proceed")
     pub message: String,
+
+    /// Every line of the reply as received, without line endings
+    pub lines: Vec<String>,
+
+    /// RFC 959 category, from the first digit of the code
+    pub category: ReplyCategory,
+
+    /// Whether the reply spanned several lines ("211-" ... "211 ")
+    pub multiline: bool,
+}
+
+/// RFC 959 reply category, given by the first digit of the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyCategory {
+    /// 1xx - action started, expect another reply
+    PositivePreliminary,
+    /// 2xx - action completed
+    PositiveCompletion,
+    /// 3xx - more information needed
+    PositiveIntermediate,
+    /// 4xx - action failed, may succeed if retried
+    TransientNegative,
+    /// 5xx - action failed
+    PermanentNegative,
+    /// Any other first digit
+    Unknown,
+}
+
+impl ReplyCategory {
+    /// Category of a reply code
+    pub fn from_code(code: u16) -> Self {
+        match code / 100 {
+            1 => Self::PositivePreliminary,
+            2 => Self::PositiveCompletion,
+            3 => Self::PositiveIntermediate,
+            4 => Self::TransientNegative,
+            5 => Self::PermanentNegative,
+            _ => Self::Unknown,
+        }
+    }
 }
 
 impl FtpResponse {
-    /// Create a new FTP response
-    pub fn new(code: u16, message: String) -> Self {
-        Self { code, message }
+    /// Build a response from the lines of one reply, as read from the server
+    pub fn from_lines(lines: Vec<String>) -> Result<Self, String> {
+        let first = lines.first().ok_or_else(|| "Empty response".to_string())?;
+        let (code, separator, message) = split_reply_line(first)?;
+
+        debug!("Parsed FTP response: code={code}, message='{message}'");
+
+        Ok(Self {
+            code,
+            message: message.to_string(),
+            category: ReplyCategory::from_code(code),
+            multiline: separator == '-' && lines.len() > 1,
+            lines,
+        })
+    }
+
+    /// Lines between the first and the last line of a multi-line reply
+    pub fn body(&self) -> &[String] {
+        match self.lines.len() {
+            0..=2 => &[],
+            len => &self.lines[1..len - 1],
+        }
+    }
+
+    /// 1xx - the action has started and another reply will follow
+    pub fn is_positive_preliminary(&self) -> bool {
+        self.category == ReplyCategory::PositivePreliminary
+    }
+
+    /// 2xx - the action completed
+    pub fn is_positive_completion(&self) -> bool {
+        self.category == ReplyCategory::PositiveCompletion
+    }
+
+    /// 4xx - the action failed but may succeed if retried
+    pub fn is_transient_negative(&self) -> bool {
+        self.category == ReplyCategory::TransientNegative
+    }
+
+    /// 5xx - the action failed
+    pub fn is_permanent_negative(&self) -> bool {
+        self.category == ReplyCategory::PermanentNegative
+    }
+
+    /// 4xx or 5xx - the reply reports a failure
+    pub fn is_negative(&self) -> bool {
+        self.is_transient_negative() || self.is_permanent_negative()
     }
 }
 
+/// The full reply text, one line per reply line
 impl std::fmt::Display for FtpResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}", self.code, self.message)
+        write!(f, "{}", self.lines.join("\n"))
     }
 }
 
-/// Parse FTP response string into structured response
-pub fn parse_response(response: &str) -> Result<FtpResponse, String> {
-    let response = response.trim();
-
-    if response.is_empty() {
-        return Err("Empty response".to_string());
-    }
+/// Split a reply line into code, separator and message
+///
+/// The separator is a space for the last line of a reply and a dash for the first
+/// line of a multi-line reply.
+pub fn split_reply_line(line: &str) -> Result<(u16, char, &str), String> {
+    let line = line.trim_end();
 
     // FTP responses start with 3-digit code followed by space or dash
-    if response.len() < 4 {
-        return Err("Response too short".to_string());
+    let code_str = line
+        .get(0..3)
+        .ok_or_else(|| "Response too short".to_string())?;
+    if !code_str.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Invalid response code: {code_str}"));
     }
-
-    // Extract the response code (first 3 characters)
-    let code_str = &response[0..3];
     let code = code_str
         .parse::<u16>()
         .map_err(|_| format!("Invalid response code: {code_str}"))?;
 
-    // Check separator (should be space for single line, dash for multi-line start)
-    let separator = response.chars().nth(3).unwrap_or(' ');
+    let separator = line[3..].chars().next().unwrap_or(' ');
     if separator != ' ' && separator != '-' {
         return Err("Invalid response format: missing separator after code".to_string());
     }
 
-    // Extract message (everything after "XXX " or "XXX-")
-    let message = if response.len() > 4 {
-        response[4..].to_string()
-    } else {
-        String::new()
+    // Message is everything after "XXX " or "XXX-"
+    let message = line.get(4..).unwrap_or("");
+    Ok((code, separator, message))
+}
+
+/// Extract the directory from a "257" PWD reply such as `257 "/home/user" is current directory`
+///
+/// Embedded quotes are doubled in the reply (RFC 959), so `""` is unescaped to `"`.
+pub fn parse_pwd_response(response: &str) -> Option<String> {
+    let response = response.trim();
+    if !response.starts_with("257") {
+        return None;
+    }
+
+    let mut chars = response[3..]
+        .chars()
+        .skip_while(|&c| c != '"')
+        .skip(1)
+        .peekable();
+    let mut path = String::new();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                path.push('"');
+            } else {
+                return Some(path);
+            }
+        } else {
+            path.push(c);
+        }
+    }
+
+    None
+}
+
+/// Extract the data address from a "227" PASV reply message
+///
+/// Accepts the RFC 959 form `(h1,h2,h3,h4,p1,p2)` as well as `(ip:port)`.
+pub fn parse_pasv_response(message: &str) -> Option<SocketAddr> {
+    let start = message.find('(')?;
+    let end = start + message[start..].find(')')?;
+    let inner = &message[start + 1..end];
+
+    if let Ok(addr) = inner.parse() {
+        return Some(addr);
+    }
+
+    let numbers: Vec<u8> = inner
+        .split(',')
+        .map(|part| part.trim().parse())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match numbers[..] {
+        [h1, h2, h3, h4, p1, p2] => Some(SocketAddr::new(
+            Ipv4Addr::new(h1, h2, h3, h4).into(),
+            u16::from_be_bytes([p1, p2]),
+        )),
+        _ => None,
+    }
+}
+
+/// Format an address as the RFC 959 PORT argument `h1,h2,h3,h4,p1,p2`
+///
+/// Returns None for IPv6 addresses, which PORT cannot carry.
+pub fn format_port_argument(addr: SocketAddr) -> Option<String> {
+    let SocketAddr::V4(addr) = addr else {
+        return None;
     };
+    let [h1, h2, h3, h4] = addr.ip().octets();
+    let [p1, p2] = addr.port().to_be_bytes();
+    Some(format!("{h1},{h2},{h3},{h4},{p1},{p2}"))
+}
 
-    debug!("Parsed FTP response: code={code}, message='{message}'");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<FtpResponse, String> {
+        FtpResponse::from_lines(text.lines().map(str::to_string).collect())
+    }
 
-    Ok(FtpResponse::new(code, message))
+    #[test]
+    fn test_parse_multiline_reply() {
+        let reply = parse("211-Features:\n MDTM\n SIZE\n211 End\n").unwrap();
+        assert_eq!(reply.code, 211);
+        assert_eq!(reply.message, "Features:");
+        assert!(reply.multiline);
+        assert!(reply.is_positive_completion());
+        assert_eq!(reply.body(), [" MDTM", " SIZE"]);
+        assert_eq!(reply.to_string(), "211-Features:\n MDTM\n SIZE\n211 End");
+
+        let reply = parse("452 Disk full").unwrap();
+        assert!(!reply.multiline);
+        assert!(reply.is_transient_negative());
+        assert!(reply.body().is_empty());
+
+        assert!(parse("hello").is_err());
+    }
+
+    #[test]
+    fn test_parse_pwd_response() {
+        assert_eq!(
+            parse_pwd_response("257 \"/home/user\" is current directory"),
+            Some("/home/user".to_string())
+        );
+        assert_eq!(
+            parse_pwd_response("257 \"/odd \"\"name\"\"\" created"),
+            Some("/odd \"name\"".to_string())
+        );
+        assert_eq!(parse_pwd_response("257 \"/unterminated"), None);
+        assert_eq!(parse_pwd_response("550 \"/home\" not found"), None);
+    }
+
+    #[test]
+    fn test_parse_pasv_response() {
+        assert_eq!(
+            parse_pasv_response("Entering Passive Mode (192,168,1,10,195,80)"),
+            Some("192.168.1.10:50000".parse().unwrap())
+        );
+        assert_eq!(
+            parse_pasv_response("Entering Passive Mode (127.0.0.1:2122)"),
+            Some("127.0.0.1:2122".parse().unwrap())
+        );
+        assert_eq!(parse_pasv_response("Entering Passive Mode (1,2,3)"), None);
+    }
+
+    #[test]
+    fn test_format_port_argument() {
+        let addr: SocketAddr = "192.168.1.10:50000".parse().unwrap();
+        let argument = format_port_argument(addr).unwrap();
+        assert_eq!(argument, "192,168,1,10,195,80");
+        assert_eq!(parse_pasv_response(&format!("({argument})")), Some(addr));
+        assert_eq!(format_port_argument("[::1]:21".parse().unwrap()), None);
+    }
 }
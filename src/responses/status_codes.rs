@@ -3,6 +3,11 @@
 // Success codes (2xx)
 pub const USER_LOGGED_IN: u16 = 230;
 
+// Intermediate codes (3xx)
+pub const USER_NAME_OK_NEED_PASSWORD: u16 = 331;
+pub const NEED_ACCOUNT: u16 = 332;
+pub const FILE_ACTION_PENDING: u16 = 350;
+
 // Client-side error codes
 pub const CLIENT_ERROR_NOT_AUTHENTICATED: u16 = 530;
 
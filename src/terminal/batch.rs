@@ -0,0 +1,136 @@
+//! Batch (script) mode support
+//!
+//! Scripts contain one command per line; blank lines and lines starting
+//! with `#` are ignored. The process exit status reports the first failure.
+
+use crate::error::RaxFtpClientError;
+
+/// Process exit status for batch runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success,
+    /// Any failure not covered by a more specific status
+    Failure,
+    /// Bad command-line usage or configuration
+    Usage,
+    ConnectionFailed,
+    AuthenticationFailed,
+    TransferFailed,
+    /// The remote file or directory does not exist (550)
+    FileNotFound,
+}
+
+impl ExitStatus {
+    /// Numeric process exit code
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Failure => 1,
+            ExitStatus::Usage => 2,
+            ExitStatus::ConnectionFailed => 3,
+            ExitStatus::AuthenticationFailed => 4,
+            ExitStatus::TransferFailed => 5,
+            ExitStatus::FileNotFound => 6,
+        }
+    }
+
+    /// Classify a client error
+    pub fn from_error(error: &RaxFtpClientError) -> Self {
+        if let Some(reply) = error.reply() {
+            return Self::from_reply_code(reply.code);
+        }
+
+        match error {
+            RaxFtpClientError::ConnectionRefused(_)
+            | RaxFtpClientError::ConnectionTimeout(_)
+            | RaxFtpClientError::ConnectionLost(_)
+            | RaxFtpClientError::NotConnected(_)
+            | RaxFtpClientError::InvalidHost(_)
+            | RaxFtpClientError::ProxyFailed(_) => ExitStatus::ConnectionFailed,
+            RaxFtpClientError::NotAuthenticated(_) => ExitStatus::AuthenticationFailed,
+            RaxFtpClientError::FileNotFound { .. } => ExitStatus::FileNotFound,
+            RaxFtpClientError::TransferFailed { .. }
+            | RaxFtpClientError::DataConnectionFailed(_)
+            | RaxFtpClientError::PermissionDenied { .. } => ExitStatus::TransferFailed,
+            RaxFtpClientError::InvalidCommand(_) => ExitStatus::Usage,
+            _ => ExitStatus::Failure,
+        }
+    }
+
+    /// Classify a negative server reply code (4xx/5xx)
+    pub fn from_reply_code(code: u16) -> Self {
+        match code {
+            421 => ExitStatus::ConnectionFailed,
+            530 | 532 => ExitStatus::AuthenticationFailed,
+            550 => ExitStatus::FileNotFound,
+            425 | 426 | 450 | 451 | 452 | 551 | 552 | 553 => ExitStatus::TransferFailed,
+            _ => ExitStatus::Failure,
+        }
+    }
+}
+
+/// Return the command on a script line, or None for blank lines and comments
+pub fn script_command(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        None
+    } else {
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::responses::FtpResponse;
+
+    #[test]
+    fn test_exit_status_from_reply_code() {
+        assert_eq!(
+            ExitStatus::from_reply_code(421),
+            ExitStatus::ConnectionFailed
+        );
+        assert_eq!(
+            ExitStatus::from_reply_code(530),
+            ExitStatus::AuthenticationFailed
+        );
+        assert_eq!(ExitStatus::from_reply_code(550), ExitStatus::FileNotFound);
+        assert_eq!(ExitStatus::from_reply_code(426), ExitStatus::TransferFailed);
+        assert_eq!(ExitStatus::from_reply_code(500), ExitStatus::Failure);
+        assert_eq!(ExitStatus::FileNotFound.code(), 6);
+    }
+
+    #[test]
+    fn test_exit_status_from_error() {
+        let reply = FtpResponse::from_lines(vec!["530 Login incorrect".to_string()]).unwrap();
+        assert_eq!(
+            ExitStatus::from_error(&RaxFtpClientError::from_reply(reply)),
+            ExitStatus::AuthenticationFailed
+        );
+        assert_eq!(
+            ExitStatus::from_error(&RaxFtpClientError::ConnectionRefused("x".to_string())),
+            ExitStatus::ConnectionFailed
+        );
+        assert_eq!(
+            ExitStatus::from_error(&RaxFtpClientError::DataConnectionFailed("x".to_string())),
+            ExitStatus::TransferFailed
+        );
+        assert_eq!(
+            ExitStatus::from_error(&RaxFtpClientError::InvalidCommand("x".to_string())),
+            ExitStatus::Usage
+        );
+        assert_eq!(
+            ExitStatus::from_error(&RaxFtpClientError::InvalidPort("x".to_string())),
+            ExitStatus::Failure
+        );
+    }
+
+    #[test]
+    fn test_script_command() {
+        assert_eq!(script_command("  RETR file.txt  "), Some("RETR file.txt"));
+        assert_eq!(script_command(""), None);
+        assert_eq!(script_command("   "), None);
+        assert_eq!(script_command("# upload the report"), None);
+        assert_eq!(script_command("  # indented comment"), None);
+    }
+}
@@ -0,0 +1,385 @@
+//! Line editing, tab completion and persistent command history for interactive sessions
+//!
+//! Uses rustyline when stdin is a terminal (arrow keys, Ctrl-R reverse
+//! search, Tab completion) and falls back to plain line reads when input is piped.
+
+use log::{debug, warn};
+use rustyline::completion::{Completer, Pair};
+use rustyline::config::{CompletionType, Config};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::cell::RefCell;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::client::RaxFtpClient;
+use crate::commands::{COMMAND_NAMES, is_secret_command};
+use crate::error::Result;
+use crate::terminal::listing::EntryType;
+
+/// Number of commands kept in the history file
+const HISTORY_SIZE: usize = 5000;
+
+/// Reads command lines, with editing and history on a terminal
+pub struct LineEditor {
+    editor: Option<Editor<CommandHelper, DefaultHistory>>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    /// Create an editor, loading saved history if stdin is a terminal
+    ///
+    /// Completion looks up remote names and the local directory through `client`.
+    pub fn new(client: Rc<RefCell<RaxFtpClient>>) -> Self {
+        let editor = if io::stdin().is_terminal() {
+            match create_editor(client) {
+                Ok(editor) => Some(editor),
+                Err(e) => {
+                    warn!("Line editing unavailable: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut line_editor = Self {
+            editor,
+            history_path: history_path(),
+        };
+        line_editor.load_history();
+        line_editor
+    }
+
+    /// Read one line; `None` at end of input
+    ///
+    /// Ctrl-C discards the current line and returns an empty one.
+    pub fn read_line(&mut self, prompt: &str) -> Result<Option<String>> {
+        let Some(editor) = &mut self.editor else {
+            print!("{prompt}");
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            return match io::stdin().lock().read_line(&mut input)? {
+                0 => Ok(None),
+                _ => Ok(Some(input)),
+            };
+        };
+
+        match editor.readline(prompt) {
+            Ok(line) => {
+                self.add_history(&line);
+                Ok(Some(line))
+            }
+            Err(ReadlineError::Interrupted) => Ok(Some(String::new())),
+            Err(ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(io::Error::other(e).into()),
+        }
+    }
+
+    /// Record a line in memory and in the history file, leaving out passwords
+    fn add_history(&mut self, line: &str) {
+        let (Some(editor), Some(line)) = (&mut self.editor, history_entry(line)) else {
+            return;
+        };
+        if let Err(e) = editor.add_history_entry(line) {
+            debug!("Failed to add history entry: {e}");
+            return;
+        }
+
+        if let Some(path) = &self.history_path
+            && let Err(e) = editor.append_history(path)
+        {
+            debug!("Failed to save history to {}: {e}", path.display());
+        }
+    }
+
+    fn load_history(&mut self) {
+        let (Some(editor), Some(path)) = (&mut self.editor, &self.history_path) else {
+            return;
+        };
+
+        if let Some(parent) = path.parent()
+            && let Err(e) = std::fs::create_dir_all(parent)
+        {
+            warn!("Cannot create history directory {}: {e}", parent.display());
+            return;
+        }
+
+        match editor.load_history(path) {
+            Ok(()) => debug!("Loaded history from {}", path.display()),
+            // No history yet
+            Err(ReadlineError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to load history from {}: {e}", path.display()),
+        }
+    }
+}
+
+/// The line as it goes into the history, or None for blank lines and
+/// commands carrying a secret (PASS, ACCT, also through QUOTE)
+fn history_entry(line: &str) -> Option<&str> {
+    let line = line.trim();
+    (!line.is_empty() && !is_secret_command(line)).then_some(line)
+}
+
+fn create_editor(
+    client: Rc<RefCell<RaxFtpClient>>,
+) -> rustyline::Result<Editor<CommandHelper, DefaultHistory>> {
+    let config = Config::builder()
+        .max_history_size(HISTORY_SIZE)?
+        .history_ignore_dups(true)?
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::with_config(config)?;
+    editor.set_helper(Some(CommandHelper { client }));
+    Ok(editor)
+}
+
+/// What the argument of a command completes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArgumentKind {
+    RemoteFile,
+    RemoteDirectory,
+    LocalPath,
+}
+
+fn argument_kind(command: &str) -> Option<ArgumentKind> {
+    match command.to_uppercase().as_str() {
+        "RETR" | "DEL" | "MDTM" | "RENAME" | "MV" => Some(ArgumentKind::RemoteFile),
+        "CWD" | "RMD" => Some(ArgumentKind::RemoteDirectory),
+        "STOR" | "LCD" | "LLS" | "LMKDIR" => Some(ArgumentKind::LocalPath),
+        _ => None,
+    }
+}
+
+/// Tab completion for command names, remote names and local paths
+pub struct CommandHelper {
+    client: Rc<RefCell<RaxFtpClient>>,
+}
+
+impl CommandHelper {
+    /// Names in the current remote directory, from the client's listing cache
+    fn remote_candidates(&self, prefix: &str, directories: bool) -> Vec<Pair> {
+        // The client is busy only while a command runs, never during readline
+        let Ok(client) = self.client.try_borrow() else {
+            return Vec::new();
+        };
+
+        client
+            .cached_entries()
+            .iter()
+            .filter(|entry| entry.name != "." && entry.name != "..")
+            .filter(|entry| (entry.entry_type == EntryType::Directory) == directories)
+            .filter(|entry| entry.name.starts_with(prefix))
+            .map(|entry| Pair {
+                display: entry.name.clone(),
+                replacement: entry.name.clone(),
+            })
+            .collect()
+    }
+
+    /// Paths under the local directory, descending into subdirectories
+    fn local_candidates(&self, prefix: &str) -> Vec<Pair> {
+        let local_directory = PathBuf::from(self.client.borrow().local_directory());
+        let (dir_part, name_prefix) = match prefix.rfind('/') {
+            Some(index) => (&prefix[..=index], &prefix[index + 1..]),
+            None => ("", prefix),
+        };
+
+        let Ok(read_dir) = std::fs::read_dir(local_directory.join(dir_part)) else {
+            return Vec::new();
+        };
+
+        let mut candidates: Vec<Pair> = read_dir
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !name.starts_with(name_prefix) || is_hidden(&name, name_prefix) {
+                    return None;
+                }
+                let suffix = if is_dir(&entry.path()) { "/" } else { "" };
+                Some(Pair {
+                    display: format!("{name}{suffix}"),
+                    replacement: format!("{dir_part}{name}{suffix}"),
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.display.cmp(&b.display));
+        candidates
+    }
+}
+
+fn is_hidden(name: &str, prefix: &str) -> bool {
+    name.starts_with('.') && !prefix.starts_with('.')
+}
+
+fn is_dir(path: &Path) -> bool {
+    path.metadata().is_ok_and(|metadata| metadata.is_dir())
+}
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.len() - before.trim_start().len();
+        let typed = &before[start..];
+
+        // First word: a command name, in the case the user is typing in
+        let Some(command_end) = typed.find(char::is_whitespace) else {
+            let lowercase = typed.chars().all(|c| c.is_ascii_lowercase());
+            let candidates = COMMAND_NAMES
+                .iter()
+                .filter(|name| name.starts_with(&typed.to_uppercase()))
+                .map(|name| {
+                    let name = if lowercase {
+                        name.to_lowercase()
+                    } else {
+                        name.to_string()
+                    };
+                    Pair {
+                        display: name.clone(),
+                        replacement: format!("{name} "),
+                    }
+                })
+                .collect();
+            return Ok((start, candidates));
+        };
+
+        // The argument is the rest of the line, so names may contain spaces
+        let rest = &typed[command_end..];
+        let arg_start = start + command_end + (rest.len() - rest.trim_start().len());
+        let prefix = &line[arg_start..pos];
+
+        let candidates = match argument_kind(&typed[..command_end]) {
+            Some(ArgumentKind::RemoteFile) => self.remote_candidates(prefix, false),
+            Some(ArgumentKind::RemoteDirectory) => self.remote_candidates(prefix, true),
+            Some(ArgumentKind::LocalPath) => self.local_candidates(prefix),
+            None => Vec::new(),
+        };
+        Ok((arg_start, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
+
+/// History file location (`$XDG_DATA_HOME` or `~/.local/share`)
+fn history_path() -> Option<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(data_home.join("rax-ftp-client").join("history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{connected_client, scripted_server};
+    use crate::commands::FtpCommand;
+    use crate::config::ClientConfig;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    fn complete(helper: &CommandHelper, line: &str) -> Vec<String> {
+        let history = DefaultHistory::new();
+        let (_, candidates) = helper
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+        candidates
+            .into_iter()
+            .map(|candidate| candidate.replacement)
+            .collect()
+    }
+
+    #[test]
+    fn test_completion_without_a_cached_listing() {
+        let client = RaxFtpClient::new(ClientConfig::default());
+        let helper = CommandHelper {
+            client: Rc::new(RefCell::new(client)),
+        };
+
+        assert_eq!(complete(&helper, "rec"), ["reconnect "]);
+        // No listing yet: nothing to offer, and nothing is sent to the server
+        assert!(complete(&helper, "RETR n").is_empty());
+        assert!(complete(&helper, "CWD ").is_empty());
+    }
+
+    #[test]
+    fn test_completion_from_the_cached_listing() {
+        let data = TcpListener::bind("127.0.0.1:0").unwrap();
+        let data_port = data.local_addr().unwrap().port();
+        let listing = std::thread::spawn(move || {
+            let (mut stream, _) = data.accept().unwrap();
+            stream
+                .write_all(b"notes.txt|120|0\r\nnews/|0|0\r\nreport.pdf|5|0\r\n")
+                .unwrap();
+        });
+        let pasv = format!(
+            "227 Entering Passive Mode (127,0,0,1,{},{})\r\n",
+            data_port >> 8,
+            data_port & 0xFF
+        );
+        let (port, server) = scripted_server(&[
+            "230 Logged in\r\n",
+            &pasv,
+            "150 Here comes the listing\r\n226 Directory send OK\r\n",
+            "250 Directory changed\r\n",
+        ]);
+
+        let mut client = connected_client(port);
+        client.login("bob", |_| Ok(None)).unwrap();
+        client.execute_command(&FtpCommand::List).unwrap();
+        listing.join().unwrap();
+
+        let helper = CommandHelper {
+            client: Rc::new(RefCell::new(client)),
+        };
+        assert_eq!(complete(&helper, "RETR n"), ["notes.txt"]);
+        assert_eq!(complete(&helper, "retr "), ["notes.txt", "report.pdf"]);
+        assert_eq!(complete(&helper, "CWD n"), ["news"]);
+
+        // Changing directory drops the listing instead of fetching a new one
+        helper
+            .client
+            .borrow_mut()
+            .execute_command(&FtpCommand::Cwd("news".to_string()))
+            .unwrap();
+        assert!(complete(&helper, "RETR n").is_empty());
+
+        drop(helper);
+        assert_eq!(
+            server.join().unwrap(),
+            ["USER bob", "PASV", "LIST", "CWD news"]
+        );
+    }
+
+    #[test]
+    fn test_secrets_stay_out_of_history() {
+        assert_eq!(history_entry("  RETR notes.txt \n"), Some("RETR notes.txt"));
+        assert_eq!(history_entry("USER alice"), Some("USER alice"));
+        assert_eq!(history_entry("PASS s3cret"), None);
+        assert_eq!(history_entry("pass s3cret"), None);
+        assert_eq!(history_entry("ACCT billing"), None);
+        assert_eq!(history_entry("QUOTE PASS s3cret"), None);
+        assert_eq!(history_entry("   "), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! JSON output for command results
+//!
+//! In JSON mode every command produces exactly one object on stdout.
+
+use serde::Serialize;
+
+use crate::commands::{CommandData, CommandOutput};
+use crate::error::RaxFtpClientError;
+
+/// One command result, as printed in JSON mode
+#[derive(Debug, Serialize)]
+pub struct JsonResult<'a> {
+    pub command: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_none_data")]
+    pub data: Option<&'a CommandData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonError>,
+}
+
+/// Error details of a failed command
+#[derive(Debug, Serialize)]
+pub struct JsonError {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl From<&RaxFtpClientError> for JsonError {
+    fn from(error: &RaxFtpClientError) -> Self {
+        Self {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+fn is_none_data(data: &Option<&CommandData>) -> bool {
+    matches!(data, None | Some(CommandData::None))
+}
+
+/// Render the result of a command as a single-line JSON object
+pub fn format_command_result(
+    command: &str,
+    result: Result<&CommandOutput, &RaxFtpClientError>,
+) -> String {
+    let json = match result {
+        Ok(output) => JsonResult {
+            command: command.to_string(),
+            ok: output.is_success(),
+            code: output.reply.as_ref().map(|reply| reply.code),
+            message: output.reply.as_ref().map(|reply| reply.message.as_str()),
+            data: Some(&output.data),
+            // Locally produced output (e.g. HELP) has no reply to carry it
+            text: output.reply.is_none().then_some(output.text.as_str()),
+            error: None,
+        },
+        Err(error) => JsonResult {
+            command: command.to_string(),
+            ok: false,
+            code: error.code(),
+            message: error.reply().map(|reply| reply.message.as_str()),
+            data: None,
+            text: None,
+            error: Some(error.into()),
+        },
+    };
+
+    serde_json::to_string(&json).unwrap_or_else(|e| {
+        serde_json::json!({
+            "command": command,
+            "ok": false,
+            "error": { "kind": "serialization", "message": e.to_string() },
+        })
+        .to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::responses::FtpResponse;
+
+    fn reply(line: &str) -> FtpResponse {
+        FtpResponse::from_lines(vec![line.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_format_successful_command() {
+        let output = CommandOutput::from_reply(reply("257 \"/pub\" is current directory"))
+            .with_data(CommandData::WorkingDirectory {
+                path: "/pub".to_string(),
+            });
+        let json: serde_json::Value =
+            serde_json::from_str(&format_command_result("PWD", Ok(&output))).unwrap();
+        assert_eq!(json["command"], "PWD");
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["code"], 257);
+        assert_eq!(json["data"]["type"], "working_directory");
+        assert_eq!(json["data"]["path"], "/pub");
+        assert!(json.get("text").is_none());
+        assert!(json.get("error").is_none());
+
+        let output = CommandOutput::local("Available commands".to_string());
+        let json: serde_json::Value =
+            serde_json::from_str(&format_command_result("HELP", Ok(&output))).unwrap();
+        assert_eq!(json["text"], "Available commands");
+        assert!(json.get("code").is_none());
+        assert!(json.get("data").is_none());
+    }
+
+    #[test]
+    fn test_format_failed_command() {
+        let error = RaxFtpClientError::from_reply(reply("550 No such file"));
+        let line = format_command_result("RETR missing.txt", Err(&error));
+        assert!(!line.contains('\n'));
+
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["ok"], false);
+        assert_eq!(json["code"], 550);
+        assert_eq!(json["message"], "No such file");
+        assert_eq!(json["error"]["kind"], "file_unavailable");
+        assert_eq!(
+            json["error"]["message"],
+            "File unavailable (550): No such file"
+        );
+    }
+}
@@ -1,18 +1,22 @@
 //! Directory listing display functionality
 
+use chrono::{DateTime, Local};
+use serde::Serialize;
 use std::fmt;
 
 /// Represents a directory entry with metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DirectoryEntry {
     pub name: String,
+    #[serde(rename = "type")]
     pub entry_type: EntryType,
     pub size: Option<u64>,
-    pub modified: Option<String>,
+    pub modified: Option<DateTime<Local>>,
 }
 
 /// Type of directory entry
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum EntryType {
     File,
     Directory,
@@ -43,12 +47,11 @@ impl DirectoryEntry {
                 let size: Option<u64> = parts[1].parse().ok().filter(|&s| s > 0);
                 let timestamp: Option<u64> = parts[2].parse().ok().filter(|&t| t > 0);
 
-                // Convert timestamp to readable format
+                // Convert timestamp to local time
                 let modified = timestamp.map(|ts| {
                     use std::time::UNIX_EPOCH;
                     let system_time = UNIX_EPOCH + std::time::Duration::from_secs(ts);
-                    let datetime: chrono::DateTime<chrono::Local> = system_time.into();
-                    datetime.format("%Y-%m-%d %H:%M").to_string()
+                    DateTime::<Local>::from(system_time)
                 });
 
                 let (entry_type, display_name) = if name == "." || name == ".." {
@@ -135,7 +138,9 @@ pub fn format_directory_listing(raw_listing: &[String]) -> String {
             name_display,
             entry.entry_type,
             entry.size.map_or("-".to_string(), format_size),
-            entry.modified.as_deref().unwrap_or("-")
+            entry.modified.map_or("-".to_string(), |time| time
+                .format("%Y-%m-%d %H:%M")
+                .to_string())
         ));
     }
 
@@ -0,0 +1,160 @@
+//! Client-side commands on the local filesystem (LCD, LLS, LMKDIR, !)
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Result;
+use crate::terminal::listing::format_directory_listing;
+
+/// Resolve `path` against the current local directory, expanding a leading `~`
+pub fn resolve_path(base: &Path, path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => base.join(path),
+    }
+}
+
+/// Check that `path` is an existing directory and return its canonical form
+pub fn change_directory(path: &Path) -> Result<PathBuf> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    if !canonical.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("'{}' is not a directory", path.display()),
+        )
+        .into());
+    }
+    Ok(canonical)
+}
+
+/// List a local directory in the same table as remote listings
+pub fn list_directory(path: &Path) -> Result<String> {
+    let mut raw_entries = Vec::new();
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        let mut name = entry.file_name().to_string_lossy().into_owned();
+        if metadata.is_dir() {
+            name.push('/');
+        }
+        let size = if metadata.is_dir() { 0 } else { metadata.len() };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+
+        // Same "name|size|timestamp" format the server sends
+        raw_entries.push(format!("{name}|{size}|{modified}"));
+    }
+
+    raw_entries.sort();
+    Ok(format_directory_listing(&raw_entries))
+}
+
+/// Create a local directory (and any missing parents)
+pub fn make_directory(path: &Path) -> Result<String> {
+    std::fs::create_dir_all(path)?;
+    Ok(format!("Created local directory {}", path.display()))
+}
+
+/// Run a shell command in `directory` with the terminal attached
+///
+/// An empty command starts an interactive shell.
+pub fn run_shell(command: &str, directory: &Path) -> Result<String> {
+    let mut process = if command.is_empty() {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| default_shell().to_string());
+        Command::new(shell)
+    } else if cfg!(windows) {
+        let mut process = Command::new(default_shell());
+        process.args(["/C", command]);
+        process
+    } else {
+        let mut process = Command::new(default_shell());
+        process.args(["-c", command]);
+        process
+    };
+
+    let status = process.current_dir(directory).status()?;
+    if status.success() {
+        Ok(String::new())
+    } else {
+        Err(io::Error::other(format!("shell command exited with {status}")).into())
+    }
+}
+
+fn default_shell() -> &'static str {
+    if cfg!(windows) { "cmd" } else { "/bin/sh" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("rax-local-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("sub")).unwrap();
+        std::fs::write(directory.join("notes.txt"), "hello").unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_lcd_resolves_relative_and_absolute_paths() {
+        let base = temp_directory("lcd");
+
+        let sub = change_directory(&resolve_path(&base, "sub")).unwrap();
+        assert_eq!(sub, base.join("sub").canonicalize().unwrap());
+        let back = change_directory(&resolve_path(&sub, "..")).unwrap();
+        assert_eq!(back, base.canonicalize().unwrap());
+        assert_eq!(resolve_path(&sub, "/tmp"), PathBuf::from("/tmp"));
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(
+                resolve_path(&base, "~/ftp"),
+                PathBuf::from(home).join("ftp")
+            );
+        }
+
+        assert!(change_directory(&base.join("notes.txt")).is_err());
+        assert!(change_directory(&base.join("missing")).is_err());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn test_lls_lists_files_and_directories() {
+        let base = temp_directory("lls");
+
+        let listing = list_directory(&base).unwrap();
+        assert!(listing.contains("notes.txt"));
+        assert!(listing.contains("5 B"));
+        assert!(listing.contains("sub"));
+        assert!(list_directory(&base.join("missing")).is_err());
+
+        make_directory(&base.join("new/nested")).unwrap();
+        assert!(base.join("new/nested").is_dir());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_runs_in_the_local_directory() {
+        let base = temp_directory("shell");
+
+        run_shell("touch made-here", &base).unwrap();
+        assert!(base.join("made-here").exists());
+        assert!(run_shell("exit 3", &base).is_err());
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! This module handles all CLI display and user interaction functionality.
 
+pub mod batch;
+pub mod editor;
+pub mod json;
 pub mod listing;
+pub mod local;
+pub mod oneshot;
 pub mod progress;
 pub mod session;
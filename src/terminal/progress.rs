@@ -1,30 +1,351 @@
 //! Progress display functionality for file transfers
+//!
+//! All progress bars share one board so several transfers can be drawn at once.
+//! On a TTY the bars are redrawn in place and sized to the terminal width; when
+//! stdout is piped, progress is reported as plain lines every 10% instead.
+//! In JSON mode every event goes to stderr as one JSON line, keeping stdout
+//! free for command results.
 
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-/// Display transfer progress bar
-pub fn display_progress(filename: &str, percentage: f64, transferred_bytes: u64, speed_bps: f64) {
-    // Create progress bar (50 characters wide)
-    let filled = (percentage / 2.0) as usize; // 50 chars = 100% / 2
-    let bar = "#".repeat(filled) + &" ".repeat(50 - filled);
+use crate::config::ProgressStyle;
+use crate::transfer::progress::TransferProgress;
 
-    print!(
-        "\r{}: [{}] {:.1}% ({}) {}",
-        filename,
-        bar,
-        percentage,
-        format_bytes(transferred_bytes),
-        format_speed(speed_bps)
-    );
+/// Terminal width used when it cannot be detected
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
 
-    if let Err(e) = io::stdout().flush() {
-        eprintln!("\nError flushing stdout: {e}");
+/// Widest progress bar drawn, regardless of terminal width
+const MAX_BAR_WIDTH: usize = 50;
+
+/// Narrowest progress bar worth drawing; below this only the numbers are shown
+const MIN_BAR_WIDTH: usize = 10;
+
+/// Interval between plain-text reports when the transfer size is unknown
+const PLAIN_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+static BOARD: Mutex<ProgressBoard> = Mutex::new(ProgressBoard::new());
+
+/// Set how progress is displayed for all subsequent transfers
+pub fn set_progress_style(style: ProgressStyle) {
+    lock_board().style = style;
+}
+
+/// Print a transfer status message ("Downloading ...", "Upload failed ...")
+pub fn report_status(message: &str) {
+    match lock_board().style {
+        ProgressStyle::Json => eprintln!(
+            "{}",
+            serde_json::json!({ "event": "status", "message": message })
+        ),
+        _ => println!("{message}"),
+    }
+}
+
+fn lock_board() -> std::sync::MutexGuard<'static, ProgressBoard> {
+    BOARD
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Handle to one progress bar on the shared board
+pub struct ProgressBar {
+    id: usize,
+    finished: bool,
+}
+
+impl ProgressBar {
+    /// Add a new progress bar for the given file
+    pub fn new(filename: &str) -> Self {
+        Self {
+            id: lock_board().add(filename),
+            finished: false,
+        }
+    }
+
+    /// Update the bar from the current transfer progress
+    pub fn update(&self, progress: &TransferProgress) {
+        lock_board().update(self.id, progress, false);
+    }
+
+    /// Show the final state of the transfer and release the bar
+    pub fn finish(mut self, progress: &TransferProgress) {
+        lock_board().update(self.id, progress, true);
+        self.finished = true;
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        // A transfer that failed part-way never reaches finish()
+        if !self.finished {
+            lock_board().abandon(self.id);
+        }
+    }
+}
+
+/// Display state of a single bar
+struct BarState {
+    id: usize,
+    label: String,
+    percentage: Option<f64>,
+    transferred_bytes: u64,
+    total_bytes: u64,
+    speed_bps: f64,
+    eta: Option<Duration>,
+    finished: bool,
+    last_decile: Option<u64>,
+    last_report: Instant,
+}
+
+impl BarState {
+    fn render_line(&self, width: usize) -> String {
+        let stats = match self.percentage {
+            Some(percentage) => format!(
+                " {:5.1}% ({}) {} ETA {}",
+                percentage,
+                format_bytes(self.transferred_bytes),
+                format_speed(self.speed_bps),
+                self.eta.map_or("--:--".to_string(), format_eta)
+            ),
+            None => format!(
+                " {} {}",
+                format_bytes(self.transferred_bytes),
+                format_speed(self.speed_bps)
+            ),
+        };
+
+        let name_width = (width / 3).clamp(8, 40);
+        let label = truncate_middle(&self.label, name_width);
+        let used = label.chars().count() + 1 + stats.chars().count();
+
+        // "[" + bar + "]" plus the separating space
+        let bar_width = width.saturating_sub(used + 3).min(MAX_BAR_WIDTH);
+        let line = match self.percentage {
+            Some(percentage) if bar_width >= MIN_BAR_WIDTH => {
+                let filled = ((percentage / 100.0 * bar_width as f64) as usize).min(bar_width);
+                format!(
+                    "{}: [{}{}]{}",
+                    label,
+                    "#".repeat(filled),
+                    " ".repeat(bar_width - filled),
+                    stats
+                )
+            }
+            _ => format!("{label}:{stats}"),
+        };
+
+        line.chars().take(width).collect()
+    }
+
+    fn render_json(&self) -> String {
+        serde_json::json!({
+            "event": if self.finished { "finished" } else { "progress" },
+            "file": self.label,
+            "bytes": self.transferred_bytes,
+            "total_bytes": (self.total_bytes > 0).then_some(self.total_bytes),
+            "percentage": self.percentage,
+            "speed_bps": self.speed_bps,
+            "eta_secs": self.eta.map(|eta| eta.as_secs()),
+        })
+        .to_string()
+    }
+
+    fn render_plain(&self) -> String {
+        match self.percentage {
+            Some(percentage) => format!(
+                "{}: {:.0}% ({} of {}) {} ETA {}",
+                self.label,
+                percentage,
+                format_bytes(self.transferred_bytes),
+                format_bytes(self.total_bytes),
+                format_speed(self.speed_bps),
+                self.eta.map_or("--:--".to_string(), format_eta)
+            ),
+            None => format!(
+                "{}: {} {}",
+                self.label,
+                format_bytes(self.transferred_bytes),
+                format_speed(self.speed_bps)
+            ),
+        }
     }
 }
 
-/// Clear the progress line and move to next line
-pub fn finish_progress() {
-    println!(); // Move to next line after progress bar
+/// Shared state for all progress bars currently on screen
+struct ProgressBoard {
+    style: ProgressStyle,
+    bars: Vec<BarState>,
+    drawn_lines: usize,
+    next_id: usize,
+}
+
+impl ProgressBoard {
+    const fn new() -> Self {
+        Self {
+            style: ProgressStyle::Auto,
+            bars: Vec::new(),
+            drawn_lines: 0,
+            next_id: 0,
+        }
+    }
+
+    /// Resolve the automatic style against the current stdout
+    fn effective_style(&self) -> ProgressStyle {
+        match self.style {
+            ProgressStyle::Auto if io::stdout().is_terminal() => ProgressStyle::Bar,
+            ProgressStyle::Auto => ProgressStyle::Plain,
+            style => style,
+        }
+    }
+
+    fn add(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.bars.push(BarState {
+            id,
+            label: label.to_string(),
+            percentage: None,
+            transferred_bytes: 0,
+            total_bytes: 0,
+            speed_bps: 0.0,
+            eta: None,
+            finished: false,
+            last_decile: None,
+            last_report: Instant::now(),
+        });
+        id
+    }
+
+    fn update(&mut self, id: usize, progress: &TransferProgress, finished: bool) {
+        let Some(bar) = self.bars.iter_mut().find(|bar| bar.id == id) else {
+            return;
+        };
+
+        bar.percentage = progress.has_known_total().then(|| progress.percentage());
+        bar.transferred_bytes = progress.transferred_bytes();
+        bar.total_bytes = progress.total_bytes();
+        bar.speed_bps = if finished {
+            progress.speed_bps()
+        } else {
+            progress.moving_speed_bps()
+        };
+        bar.eta = progress.eta();
+        bar.finished = finished;
+
+        match self.effective_style() {
+            ProgressStyle::Bar => self.redraw(),
+            ProgressStyle::Plain => self.report_plain(id),
+            ProgressStyle::Json => self.report_json(id),
+            _ => {}
+        }
+
+        self.release_finished();
+    }
+
+    /// Drop a bar whose transfer ended without finishing
+    fn abandon(&mut self, id: usize) {
+        if let Some(bar) = self.bars.iter_mut().find(|bar| bar.id == id) {
+            bar.finished = true;
+        }
+        self.release_finished();
+    }
+
+    /// Redraw every bar in place, moving the cursor back over the previous frame
+    fn redraw(&mut self) {
+        let width = terminal_width().saturating_sub(1);
+        let mut output = String::new();
+
+        if self.drawn_lines > 0 {
+            output.push_str(&format!("\x1b[{}A", self.drawn_lines));
+        }
+        for bar in &self.bars {
+            output.push_str("\r\x1b[2K");
+            output.push_str(&bar.render_line(width));
+            output.push('\n');
+        }
+        self.drawn_lines = self.bars.len();
+
+        print!("{output}");
+        flush_stdout();
+    }
+
+    /// Print a plain line when a bar crosses a 10% step (or periodically if the size is unknown)
+    fn report_plain(&mut self, id: usize) {
+        let Some(bar) = self.bars.iter_mut().find(|bar| bar.id == id) else {
+            return;
+        };
+
+        let due = match bar.percentage {
+            Some(percentage) => {
+                let decile = (percentage / 10.0) as u64;
+                let due = bar.last_decile.is_none_or(|last| decile > last);
+                bar.last_decile = Some(decile);
+                due
+            }
+            None => bar.finished || bar.last_report.elapsed() >= PLAIN_REPORT_INTERVAL,
+        };
+
+        if due {
+            bar.last_report = Instant::now();
+            println!("{}", bar.render_plain());
+        }
+    }
+
+    /// Emit one JSON progress event on stderr
+    fn report_json(&self, id: usize) {
+        if let Some(bar) = self.bars.iter().find(|bar| bar.id == id) {
+            eprintln!("{}", bar.render_json());
+        }
+    }
+
+    /// Forget the bars once every transfer on the board is done
+    fn release_finished(&mut self) {
+        if self.bars.iter().all(|bar| bar.finished) {
+            self.bars.clear();
+            self.drawn_lines = 0;
+        }
+    }
+}
+
+/// Current terminal width in columns
+fn terminal_width() -> usize {
+    if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+        return width as usize;
+    }
+
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(DEFAULT_TERMINAL_WIDTH)
+}
+
+/// Shorten a name to `max_width` characters, keeping its start and its extension
+fn truncate_middle(name: &str, max_width: usize) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= max_width {
+        return name.to_string();
+    }
+    if max_width <= 3 {
+        return chars[..max_width].iter().collect();
+    }
+
+    let keep = max_width - 3;
+    let tail = keep / 2;
+    let head = keep - tail;
+    format!(
+        "{}...{}",
+        chars[..head].iter().collect::<String>(),
+        chars[chars.len() - tail..].iter().collect::<String>()
+    )
+}
+
+fn flush_stdout() {
+    if let Err(e) = io::stdout().flush() {
+        eprintln!("\nError flushing stdout: {e}");
+    }
 }
 
 /// Format bytes as human readable string
@@ -50,6 +371,18 @@ pub fn format_speed(bps: f64) -> String {
     format!("{}/s", format_bytes(bps as u64))
 }
 
+/// Format a remaining time as "MM:SS" or "H:MM:SS"
+pub fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,4 +403,35 @@ mod tests {
         assert_eq!(format_speed(1024.0), "1.0 KB/s");
         assert_eq!(format_speed(1048576.0), "1.0 MB/s");
     }
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(Duration::from_secs(0)), "00:00");
+        assert_eq!(format_eta(Duration::from_secs(75)), "01:15");
+        assert_eq!(format_eta(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("short.txt", 20), "short.txt");
+        assert_eq!(
+            truncate_middle("a_very_long_archive_name.tar.gz", 15),
+            "a_very...tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_render_line_fits_width() {
+        let mut progress = TransferProgress::new(1000);
+        progress.add_bytes(500);
+
+        let mut board = ProgressBoard::new();
+        let id = board.add("a_very_long_archive_name_for_testing.tar.gz");
+        let bar = board.bars.iter_mut().find(|bar| bar.id == id).unwrap();
+        bar.percentage = Some(progress.percentage());
+
+        for width in [20, 40, 79, 200] {
+            assert!(bar.render_line(width).chars().count() <= width);
+        }
+    }
 }
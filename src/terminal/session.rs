@@ -3,17 +3,29 @@
 //! Handles user interaction and coordinates between parser and client.
 
 use log::{debug, error, info};
-use std::io::{self, Write};
+use std::cell::RefCell;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::rc::Rc;
 
-use crate::client::RaxFtpClient;
-use crate::commands::parse_command;
-use crate::config::ClientConfig;
-use crate::error::Result;
+use crate::client::{LoginPrompt, RaxFtpClient};
+use crate::commands::{CommandOutput, FtpCommand, parse_command, redact_secrets};
+use crate::config::{ClientConfig, OutputFormat, ProgressStyle};
+use crate::error::{RaxFtpClientError, Result};
+use crate::terminal::batch::{ExitStatus, script_command};
+use crate::terminal::editor::LineEditor;
+use crate::terminal::json::format_command_result;
+use crate::terminal::oneshot::Step;
+use crate::terminal::progress::set_progress_style;
 
 /// Terminal handler for interactive FTP sessions
 pub struct Terminal {
-    client: RaxFtpClient,
+    client: Rc<RefCell<RaxFtpClient>>,
     config: ClientConfig,
+    output: OutputFormat,
+    /// Whether a user is at the prompt (LOGIN and USER then ask for what is missing)
+    ///
+    /// Scripts never prompt: with `-b -` stdin is the script itself.
+    interactive: bool,
 }
 
 impl Terminal {
@@ -24,104 +36,404 @@ impl Terminal {
             config.display_name()
         );
 
-        Self { client, config }
+        let mut terminal = Self {
+            output: config.output,
+            interactive: false,
+            client: Rc::new(RefCell::new(client)),
+            config,
+        };
+        terminal.set_output_format(terminal.output);
+        terminal
+    }
+
+    /// Switch between text and JSON output
+    pub fn set_output_format(&mut self, output: OutputFormat) {
+        self.output = output;
+
+        // JSON output keeps stdout for results, so progress moves to JSON lines on stderr
+        set_progress_style(match output {
+            OutputFormat::Json => ProgressStyle::Json,
+            OutputFormat::Text => self.config.progress,
+        });
     }
 
     /// Run the interactive FTP session with automatic connection attempt
     pub fn run_interactive(&mut self) -> Result<()> {
-        // Show initial state (disconnected)
-        println!("RAX FTP Client - Interactive Session");
-        println!("Connected to: {}", self.config.display_name());
-        println!("Current state: {}", self.client.get_state());
-        println!("Type 'HELP' for available commands or 'QUIT' to exit");
-        println!();
+        let json = self.output == OutputFormat::Json;
+        self.interactive = true;
+
+        if !json {
+            // Show initial state (disconnected)
+            println!("RAX FTP Client - Interactive Session");
+            println!("Connected to: {}", self.client.borrow().server_name());
+            println!("Current state: {}", self.client.borrow().get_state());
+            println!("Type 'HELP' for available commands or 'QUIT' to exit");
+            println!();
+
+            println!("Attempting to connect to server...");
+        }
 
         // Attempt automatic connection
-        println!("Attempting to connect to server...");
-        match self.client.connect_with_retries() {
-            Ok(()) => {
-                println!("Connected successfully! State: {}", self.client.get_state());
-            }
-            Err(e) => {
-                println!("Connection failed: {e}");
-                println!("Continuing in disconnected mode...");
-            }
+        if self.connect_and_report().is_err() && !json {
+            println!("Continuing in disconnected mode...");
+        }
+        if !json {
+            println!();
         }
-        println!();
 
         // Continue with interactive session
-        let stdin = io::stdin();
+        let mut editor = LineEditor::new(Rc::clone(&self.client));
         loop {
-            // Show prompt with current state
-            print!("rax-ftp-client ({})> ", self.client.get_state());
-            io::stdout().flush()?;
+            // Show prompt with current state (JSON output has no prompt)
+            let prompt = match self.output {
+                OutputFormat::Text => self.prompt(),
+                OutputFormat::Json => String::new(),
+            };
 
             // Read user input
-            let mut input = String::new();
-            match stdin.read_line(&mut input) {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    let command = input.trim();
-                    if command.is_empty() {
-                        continue;
-                    }
-
-                    debug!("User entered command: {command}");
-
-                    // Parse command and handle it
-                    match self.handle_command(command) {
-                        Ok(should_continue) => {
-                            if !should_continue {
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            error!("Command failed: {e}");
-                            println!("Error: {e}");
-                        }
-                    }
-                }
+            let input = match editor.read_line(&prompt) {
+                Ok(Some(input)) => input,
+                Ok(None) => break, // EOF
                 Err(e) => {
                     error!("Failed to read input: {e}");
-                    return Err(e.into());
+                    return Err(e);
+                }
+            };
+
+            let command = input.trim();
+            if command.is_empty() {
+                continue;
+            }
+
+            debug!("User entered command: {}", redact_secrets(command));
+
+            // Parse command and handle it
+            if self.handle_command(command) == CommandStatus::Closed {
+                break;
+            }
+        }
+
+        self.close_session()
+    }
+
+    /// Run commands from a script without prompts, returning the exit status
+    ///
+    /// Stops at the first failed command unless `continue_on_error` is set,
+    /// in which case the status of the first failure is still reported.
+    pub fn run_batch(
+        &mut self,
+        script: impl BufRead,
+        continue_on_error: bool,
+    ) -> Result<ExitStatus> {
+        if self.connect_and_report().is_err() {
+            return Ok(ExitStatus::ConnectionFailed);
+        }
+
+        let mut status = ExitStatus::Success;
+        for line in script.lines() {
+            let line = line?;
+            let Some(command) = script_command(&line) else {
+                continue;
+            };
+
+            debug!("Batch command: {}", redact_secrets(command));
+
+            match self.handle_command(command) {
+                CommandStatus::Continue => {}
+                CommandStatus::Closed => break,
+                CommandStatus::Failed(failure) => {
+                    if status == ExitStatus::Success {
+                        status = failure;
+                    }
+                    if !continue_on_error {
+                        info!(
+                            "Stopping batch after failed command: {}",
+                            redact_secrets(command)
+                        );
+                        break;
+                    }
                 }
             }
         }
 
-        // Cleanup - only disconnect if still connected
+        self.close_session()?;
+        Ok(status)
+    }
+
+    /// Connect to the configured server and report the outcome
+    fn connect_and_report(&mut self) -> Result<()> {
+        let result = self.client.borrow_mut().connect_with_retries();
+
+        match self.output {
+            OutputFormat::Json => {
+                let output = CommandOutput::local(self.connected_text());
+                println!(
+                    "{}",
+                    format_command_result("CONNECT", result.as_ref().map(|()| &output))
+                );
+            }
+            OutputFormat::Text => match &result {
+                Ok(()) => {
+                    println!(
+                        "{} State: {}",
+                        self.connected_text(),
+                        self.client.borrow().get_state()
+                    );
+                }
+                Err(e) => println!("Connection failed: {e}"),
+            },
+        }
+
+        if result.is_ok() {
+            self.netrc_login();
+        }
+
+        result
+    }
+
+    /// "Connected to <server> (<address>)", naming the address actually used
+    fn connected_text(&self) -> String {
+        let client = self.client.borrow();
+        match client.connected_address() {
+            Some(addr) => format!("Connected to {} ({addr}).", client.server_name()),
+            None => format!("Connected to {}.", client.server_name()),
+        }
+    }
+
+    /// Log in from the netrc file, if it has an entry for the server
+    fn netrc_login(&mut self) {
+        match self.client.borrow_mut().netrc_login() {
+            Ok(Some(output)) => self.display_result("LOGIN (netrc)", Ok(&output)),
+            Ok(None) => {}
+            Err(e) => self.display_result("LOGIN (netrc)", Err(&e)),
+        }
+    }
+
+    /// Prompt showing the current server and state
+    fn prompt(&self) -> String {
+        if self.client.borrow().is_connected() {
+            format!(
+                "rax-ftp-client {} ({})> ",
+                self.client.borrow().server_name(),
+                self.client.borrow().get_state()
+            )
+        } else {
+            format!("rax-ftp-client ({})> ", self.client.borrow().get_state())
+        }
+    }
+
+    /// Disconnect if the server has not already closed the connection
+    fn close_session(&mut self) -> Result<()> {
         // (QUIT command may have already handled the disconnection)
-        if self.client.is_connected() {
-            println!("Closing remaining connection...");
-            self.client.disconnect()?;
+        if self.client.borrow().is_connected() {
+            if self.output == OutputFormat::Text {
+                println!("Closing remaining connection...");
+            }
+            self.client.borrow_mut().disconnect()?;
         }
         Ok(())
     }
 
-    /// Handle a user command using parser and client communication
-    fn handle_command(&mut self, input: &str) -> Result<bool> {
-        let parsed_command = parse_command(input);
-
-        // Pass all commands directly to the client for execution
-        match self.client.execute_command(&parsed_command) {
-            Ok(response) => {
-                // Display response to user
-                print!("{response}");
-                if !response.ends_with('\n') {
-                    println!(); // Ensure newline
-                }
+    /// Run one-shot steps, stopping at the first failure
+    pub fn run_steps(&mut self, steps: Vec<Step>) -> Result<ExitStatus> {
+        if self.connect_and_report().is_err() {
+            return Ok(ExitStatus::ConnectionFailed);
+        }
 
-                // Check if client state changed to disconnected (for QUIT command)
-                if !self.client.is_connected() {
-                    println!("Connection closed by server. Closing session...");
-                    return Ok(false); // Exit the interactive loop
+        let mut status = ExitStatus::Success;
+        for step in steps {
+            match self.run_step(step) {
+                CommandStatus::Continue => {}
+                CommandStatus::Closed => break,
+                CommandStatus::Failed(failure) => {
+                    status = failure;
+                    break;
                 }
+            }
+        }
+
+        self.close_session()?;
+        Ok(status)
+    }
+
+    /// Handle a user command using parser and client communication
+    fn handle_command(&mut self, input: &str) -> CommandStatus {
+        self.run_step(Step::Command(parse_command(input)))
+    }
+
+    /// Execute a single step and display its result
+    fn run_step(&mut self, step: Step) -> CommandStatus {
+        let was_connected = self.client.borrow().is_connected();
+        let quit = matches!(step, Step::Command(FtpCommand::Quit));
+        let close = matches!(step, Step::Command(FtpCommand::Close));
+
+        let (label, result) = match step {
+            // Output format is a terminal setting, not something the client executes
+            Step::Command(FtpCommand::Output(format)) => {
+                self.set_output_format(format);
+                (
+                    FtpCommand::Output(format).to_string(),
+                    Ok(CommandOutput::local(format!(
+                        "Output format set to {format}"
+                    ))),
+                )
+            }
+            // Logging in prompts for what the command line did not give
+            Step::Command(FtpCommand::Login(user)) if self.interactive => {
+                let label = FtpCommand::Login(user.clone()).to_string();
+                (label, self.login(user))
+            }
+            Step::Command(FtpCommand::User(user)) if self.interactive => {
+                let label = FtpCommand::User(user.clone()).to_string();
+                (label, self.login(Some(user)))
+            }
+            // Pass all other commands directly to the client for execution
+            Step::Command(command) => (
+                command.to_string(),
+                self.client.borrow_mut().execute_command(&command),
+            ),
+            Step::Login(user) => (
+                FtpCommand::Login(Some(user.clone())).to_string(),
+                self.login(Some(user)),
+            ),
+            Step::Retrieve { remote, local } => (
+                format!("RETR {remote}"),
+                self.client.borrow_mut().retrieve_file(&remote, &local),
+            ),
+            Step::Store { local, remote } => (
+                format!("STOR {remote}"),
+                self.client.borrow_mut().store_file(&local, &remote),
+            ),
+        };
 
-                Ok(true) // Continue with session
+        self.display_result(&label, result.as_ref());
+
+        let status = match &result {
+            Ok(output) if output.is_success() => CommandStatus::Continue,
+            Ok(output) => {
+                CommandStatus::Failed(output.reply.as_ref().map_or(ExitStatus::Failure, |reply| {
+                    ExitStatus::from_reply_code(reply.code)
+                }))
             }
-            Err(e) => {
-                println!("Command failed: {e}");
-                Ok(true) // Continue with session despite error
+            Err(e) => CommandStatus::Failed(ExitStatus::from_error(e)),
+        };
+
+        if quit {
+            return CommandStatus::Closed;
+        }
+
+        // Check if the server dropped the connection (CLOSE disconnects on purpose)
+        if was_connected && !close && result.is_ok() && !self.client.borrow().is_connected() {
+            if self.output == OutputFormat::Text {
+                println!("Connection closed by server. Closing session...");
             }
+            return CommandStatus::Closed; // Exit the session loop
         }
+
+        status // Continue with session despite error
     }
+
+    /// Log in, prompting for the username if needed and for the password with echo off
+    fn login(&mut self, user: Option<String>) -> Result<CommandOutput> {
+        let user = match user {
+            Some(user) => user,
+            None => self.prompt_username()?,
+        };
+
+        self.client.borrow_mut().login(&user, |prompt| {
+            let label = match prompt {
+                LoginPrompt::Password => "Password: ",
+                LoginPrompt::Account => "Account: ",
+                LoginPrompt::GatewayPassword => "Gateway password: ",
+            };
+            read_secret(label)
+                .map(Some)
+                .map_err(|e| RaxFtpClientError::NotAuthenticated(e.to_string()))
+        })
+    }
+
+    /// Ask for a username, defaulting to the local user name
+    fn prompt_username(&self) -> Result<String> {
+        let default_user = std::env::var("USER").unwrap_or_default();
+        if self.output == OutputFormat::Text {
+            if default_user.is_empty() {
+                print!("Name ({}): ", self.client.borrow().server_name());
+            } else {
+                print!(
+                    "Name ({}:{default_user}): ",
+                    self.client.borrow().server_name()
+                );
+            }
+            io::stdout().flush()?;
+        }
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let user = match input.trim() {
+            "" => default_user,
+            user => user.to_string(),
+        };
+
+        if user.is_empty() {
+            return Err(RaxFtpClientError::NotAuthenticated(
+                "No username given".to_string(),
+            ));
+        }
+        Ok(user)
+    }
+
+    /// Print a command result in the current output format
+    fn display_result(
+        &self,
+        label: &str,
+        result: std::result::Result<&CommandOutput, &RaxFtpClientError>,
+    ) {
+        match self.output {
+            OutputFormat::Json => println!("{}", format_command_result(label, result)),
+            OutputFormat::Text => match result {
+                // Commands like "!ls" print directly and have nothing to add
+                Ok(output) if output.text.is_empty() => {}
+                Ok(output) => {
+                    // Display response to user
+                    print!("{}", output.text);
+                    if !output.text.ends_with('\n') {
+                        println!(); // Ensure newline
+                    }
+                }
+                Err(e) => {
+                    error!("Command failed: {e}");
+                    println!("Command failed: {e}");
+                }
+            },
+        }
+    }
+}
+
+/// Read a password without echoing it
+///
+/// Prompts on the terminal; piped input is read from stdin as is.
+fn read_secret(prompt: &str) -> io::Result<String> {
+    if io::stdin().is_terminal() {
+        rpassword::prompt_password(prompt)
+    } else {
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "No {} given: stdin is closed",
+                    prompt.trim_end_matches([':', ' ']).to_lowercase()
+                ),
+            ));
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Outcome of a single command, as seen by the session loops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandStatus {
+    Continue,
+    Failed(ExitStatus),
+    Closed,
 }
@@ -1,26 +1,50 @@
 //! File download functionality
 
-use log::{debug, error, info};
-use std::fs::File;
+use log::{debug, error, info, warn};
+use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
 use crate::connection::data::DataConnection;
 use crate::error::{RaxFtpClientError, Result};
-use crate::terminal::progress::{display_progress, finish_progress, format_bytes};
-use crate::transfer::progress::TransferProgress;
+use crate::terminal::progress::{ProgressBar, format_bytes, report_status};
+use crate::transfer::progress::{TransferProgress, TransferSummary};
 
 /// Download a file through the data connection with progress tracking
+///
+/// `expected_size` comes from SIZE, the 150 reply or a listing; without it
+/// progress is shown as a running byte count instead of a percentage. A
+/// non-zero `offset` (after REST) appends to the partial local file instead.
 pub fn download_file_with_progress(
     data_connection: &mut DataConnection,
     local_path: &Path,
     filename: &str,
-) -> Result<()> {
-    info!("Starting download of '{filename}'");
-    println!("Downloading '{filename}'...");
+    expected_size: Option<u64>,
+    offset: u64,
+) -> Result<TransferSummary> {
+    match expected_size {
+        Some(size) => {
+            info!("Starting download of '{filename}' ({size} bytes)");
+            report_status(&format!(
+                "Downloading '{}' ({})...",
+                filename,
+                format_bytes(size)
+            ));
+        }
+        None => {
+            info!("Starting download of '{filename}' (size unknown)");
+            report_status(&format!("Downloading '{filename}'..."));
+        }
+    }
 
-    // Create the local file
-    let file = File::create(local_path).map_err(|e| RaxFtpClientError::TransferFailed {
+    // Create the local file, or continue the partial one when resuming
+    let file = if offset > 0 {
+        info!("Resuming '{filename}' at byte {offset}");
+        OpenOptions::new().append(true).open(local_path)
+    } else {
+        File::create(local_path)
+    };
+    let file = file.map_err(|e| RaxFtpClientError::TransferFailed {
         code: 550,
         message: format!("Cannot create local file '{}': {}", local_path.display(), e),
     })?;
@@ -30,8 +54,8 @@ pub fn download_file_with_progress(
     let mut buffer = [0u8; 8192]; // 8KB buffer
     let mut total_received = 0u64;
 
-    // We don't know the file size ahead of time, so start with 0 and update as we go
-    let mut progress = TransferProgress::new(0);
+    let mut progress = TransferProgress::new(expected_size.unwrap_or(0));
+    let progress_bar = ProgressBar::new(filename);
 
     loop {
         // Receive chunk from data connection
@@ -46,28 +70,18 @@ pub fn download_file_with_progress(
                 match writer.write_all(&buffer[..bytes_received]) {
                     Ok(()) => {
                         total_received += bytes_received as u64;
-
-                        // Update progress tracker total size as we go (since we don't know it beforehand)
-                        if progress.total_bytes() < total_received {
-                            progress = TransferProgress::new(total_received);
-                        }
                         progress.add_bytes(bytes_received as u64);
 
-                        // Update progress display every 64KB or at intervals
-                        if total_received % 65536 == 0 {
-                            display_progress(
-                                filename,
-                                100.0, // Show as 100% since we don't know total size
-                                total_received,
-                                progress.speed_bps(),
-                            );
+                        // Refresh the progress display at a fixed time interval
+                        if progress.should_display() {
+                            progress_bar.update(&progress);
                         }
 
                         debug!("Received {bytes_received} bytes, total: {total_received}");
                     }
                     Err(e) => {
                         error!("Failed to write to local file: {e}");
-                        println!("\nDownload failed: Failed to write to file");
+                        report_status("Download failed: Failed to write to file");
                         return Err(RaxFtpClientError::TransferFailed {
                             code: 550,
                             message: format!("Failed to write to file: {e}"),
@@ -77,7 +91,7 @@ pub fn download_file_with_progress(
             }
             Err(e) => {
                 error!("Failed to receive data: {e}");
-                println!("\nDownload failed: {e}");
+                report_status(&format!("Download failed: {e}"));
                 return Err(e);
             }
         }
@@ -92,21 +106,41 @@ pub fn download_file_with_progress(
         });
     }
 
+    if progress.has_known_total() && total_received != progress.total_bytes() {
+        warn!(
+            "Received {total_received} bytes for '{filename}', expected {}",
+            progress.total_bytes()
+        );
+    }
+
     // Final progress display
-    display_progress(filename, 100.0, total_received, progress.speed_bps());
-    finish_progress(); // Move to next line after progress bar
+    progress_bar.finish(&progress);
 
     info!(
         "Download completed: {} bytes in {:?}",
         total_received,
         progress.elapsed()
     );
-    println!(
+    report_status(&format!(
         "Download completed: {} ({})",
         filename,
         format_bytes(total_received)
-    );
-    Ok(())
+    ));
+    Ok(progress.summary(filename))
+}
+
+/// Extract the transfer size from a 150 reply such as
+/// "150 Opening BINARY mode data connection for file.bin (1234 bytes)"
+pub fn parse_transfer_size(response: &str) -> Option<u64> {
+    let start = response.rfind('(')?;
+    let end = start + response[start..].find(')')?;
+
+    let mut parts = response[start + 1..end].split_whitespace();
+    let size = parts.next()?.parse().ok()?;
+    match parts.next() {
+        Some(unit) if unit.eq_ignore_ascii_case("bytes") => Some(size),
+        _ => None,
+    }
 }
 
 /// Validate that a directory can be written to for downloads
@@ -0,0 +1,232 @@
+//! Server-to-server (FXP) transfers
+//!
+//! The source server is put in passive mode and its address handed to the
+//! destination with PORT; STOR and RETR then run at the same time and the
+//! data flows between the two servers without passing through the client.
+
+use log::{debug, info, warn};
+use std::io;
+
+use crate::client::RaxFtpClient;
+use crate::commands::CommandOutput;
+use crate::error::{RaxFtpClientError, Result};
+use crate::responses::{FtpResponse, format_port_argument, parse_pasv_response};
+
+/// Copy `source_path` on `source` to `destination_path` on `destination`
+///
+/// Both sessions must be logged in. Fails with a `TransferFailed` error
+/// naming the server and command when either side refuses its part.
+pub fn fxp_transfer(
+    source: &mut RaxFtpClient,
+    source_path: &str,
+    destination: &mut RaxFtpClient,
+    destination_path: &str,
+) -> Result<CommandOutput> {
+    for (side, client) in [("source", &*source), ("destination", &*destination)] {
+        if !client.is_authenticated() {
+            return Err(RaxFtpClientError::NotAuthenticated(format!(
+                "FXP needs a logged-in {side} session ({})",
+                client.server_name()
+            )));
+        }
+    }
+
+    let mut lines = Vec::new();
+
+    // 1. The source listens for the data connection
+    let pasv = command(source, "source", "PASV", &mut lines)?;
+    let Some(reported) = parse_pasv_response(&pasv.message) else {
+        return Err(RaxFtpClientError::InvalidResponse(format!(
+            "FXP: could not parse the source's PASV reply: {pasv}"
+        )));
+    };
+    let address = source.passive_target(reported)?;
+    info!("FXP: source listening on {address}");
+    let Some(port_argument) = format_port_argument(address) else {
+        return Err(RaxFtpClientError::InvalidResponse(format!(
+            "FXP: PORT cannot carry the source's IPv6 address {address}"
+        )));
+    };
+
+    // 2. The destination is told to connect there, in the RFC 959 form any server accepts
+    command(
+        destination,
+        "destination",
+        &format!("PORT {port_argument}"),
+        &mut lines,
+    )?;
+
+    // 3. Start both halves; STOR first so the destination is ready to receive
+    command(
+        destination,
+        "destination",
+        &format!("STOR {destination_path}"),
+        &mut lines,
+    )?;
+    if let Err(e) = command(source, "source", &format!("RETR {source_path}"), &mut lines) {
+        abort(destination);
+        return Err(e);
+    }
+
+    // 4. Both servers report the end of the transfer
+    let source_done = final_reply(source, "source", &mut lines);
+    let destination_done = final_reply(destination, "destination", &mut lines)?;
+    source_done?;
+
+    Ok(CommandOutput::from_lines(lines, &destination_done))
+}
+
+/// Send one command and require a positive preliminary or completion reply
+fn command(
+    client: &mut RaxFtpClient,
+    side: &str,
+    line: &str,
+    lines: &mut Vec<String>,
+) -> Result<FtpResponse> {
+    client.send_command(line)?;
+    let response = client.read_response()?;
+    lines.push(format!("{side}: {response}"));
+
+    if response.is_positive_preliminary() || response.is_positive_completion() {
+        Ok(response)
+    } else {
+        Err(refused(side, line, &response))
+    }
+}
+
+/// Wait for the reply that ends a transfer, however long the transfer takes
+fn final_reply(
+    client: &mut RaxFtpClient,
+    side: &str,
+    lines: &mut Vec<String>,
+) -> Result<FtpResponse> {
+    let response = loop {
+        match client.read_response() {
+            // The control connection stays quiet while the servers transfer
+            Err(RaxFtpClientError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                debug!("FXP: {side} still transferring");
+            }
+            result => break result?,
+        }
+    };
+    lines.push(format!("{side}: {response}"));
+
+    if response.is_positive_completion() {
+        Ok(response)
+    } else {
+        Err(RaxFtpClientError::TransferFailed {
+            code: response.code,
+            message: format!("FXP transfer failed on the {side} server: {response}"),
+        })
+    }
+}
+
+/// Cancel the destination's STOR after the source refused RETR
+fn abort(destination: &mut RaxFtpClient) {
+    if let Err(e) = destination.send_command("ABOR") {
+        warn!("FXP: could not abort the destination's STOR: {e}");
+        return;
+    }
+    // ABOR is answered with 426 for the aborted STOR and then 226 (or just 225/226)
+    while let Ok(response) = destination.read_response() {
+        debug!("FXP: destination replied to ABOR: {response}");
+        if !response.is_positive_preliminary() && response.code != 426 {
+            break;
+        }
+    }
+}
+
+fn refused(side: &str, command: &str, response: &FtpResponse) -> RaxFtpClientError {
+    let verb = command.split_whitespace().next().unwrap_or(command);
+    RaxFtpClientError::TransferFailed {
+        code: response.code,
+        message: format!("FXP refused by the {side} server ({verb}): {response}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{connected_client, scripted_server};
+
+    const LOGGED_IN: &str = "230 Logged in\r\n";
+    const PASV: &str = "227 Entering Passive Mode (127,0,0,1,195,80)\r\n";
+
+    fn logged_in_client(port: u16) -> RaxFtpClient {
+        let mut client = connected_client(port);
+        client.login("bob", |_| Ok(None)).unwrap();
+        client
+    }
+
+    #[test]
+    fn test_fxp_hands_the_source_address_to_the_destination() {
+        let (source_port, source_server) = scripted_server(&[
+            LOGGED_IN,
+            PASV,
+            "150 Opening data connection\r\n226 Transfer complete\r\n",
+        ]);
+        let (destination_port, destination_server) = scripted_server(&[
+            LOGGED_IN,
+            "200 PORT command successful\r\n",
+            "150 Ready to receive\r\n226 File stored\r\n",
+        ]);
+        let mut source = logged_in_client(source_port);
+        let mut destination = logged_in_client(destination_port);
+
+        let output =
+            fxp_transfer(&mut source, "/pub/big.iso", &mut destination, "/in/big.iso").unwrap();
+        assert_eq!(output.reply.unwrap().code, 226);
+
+        drop((source, destination));
+        assert_eq!(
+            source_server.join().unwrap(),
+            ["USER bob", "PASV", "RETR /pub/big.iso"]
+        );
+        assert_eq!(
+            destination_server.join().unwrap(),
+            ["USER bob", "PORT 127,0,0,1,195,80", "STOR /in/big.iso"]
+        );
+    }
+
+    #[test]
+    fn test_fxp_aborts_the_destination_when_retr_is_refused() {
+        let (source_port, source_server) =
+            scripted_server(&[LOGGED_IN, PASV, "550 No such file\r\n"]);
+        let (destination_port, destination_server) = scripted_server(&[
+            LOGGED_IN,
+            "200 PORT command successful\r\n",
+            "150 Ready to receive\r\n",
+            "426 Transfer aborted\r\n226 ABOR successful\r\n",
+        ]);
+        let mut source = logged_in_client(source_port);
+        let mut destination = logged_in_client(destination_port);
+
+        let error =
+            fxp_transfer(&mut source, "/pub/missing", &mut destination, "/in/missing").unwrap_err();
+        assert!(matches!(
+            &error,
+            RaxFtpClientError::TransferFailed { code: 550, message }
+                if message.starts_with("FXP refused by the source server (RETR)")
+        ));
+
+        drop((source, destination));
+        assert_eq!(
+            source_server.join().unwrap(),
+            ["USER bob", "PASV", "RETR /pub/missing"]
+        );
+        assert_eq!(
+            destination_server.join().unwrap(),
+            [
+                "USER bob",
+                "PORT 127,0,0,1,195,80",
+                "STOR /in/missing",
+                "ABOR"
+            ]
+        );
+    }
+}
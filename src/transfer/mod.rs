@@ -1,11 +1,17 @@
 //! File transfer module for RAX FTP Client
 
 pub mod download;
+pub mod fxp;
 pub mod listing;
+pub mod mtime;
 pub mod progress;
 pub mod upload;
 
 // Re-export main functions
-pub use download::{download_file_with_progress, validate_download_path};
+pub use download::{download_file_with_progress, parse_transfer_size, validate_download_path};
+pub use fxp::fxp_transfer;
 pub use listing::read_directory_listing;
+pub use mtime::{
+    format_ftp_timestamp, format_remote_time, get_local_mtime, parse_mdtm_response, set_local_mtime,
+};
 pub use upload::{upload_file_with_progress, validate_upload_file};
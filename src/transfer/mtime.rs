@@ -0,0 +1,103 @@
+//! Modification time handling for FTP transfers (MDTM / MFMT)
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use log::{debug, info};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::error::{RaxFtpClientError, Result};
+
+/// FTP timestamp format used by MDTM and MFMT (RFC 3659 `time-val`)
+const FTP_TIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Parse an RFC 3659 time value ("YYYYMMDDHHMMSS[.sss]") as UTC
+pub fn parse_ftp_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+
+    // Drop the optional fractional seconds part
+    let whole = value.split('.').next().unwrap_or(value);
+
+    NaiveDateTime::parse_from_str(whole, FTP_TIME_FORMAT)
+        .map(|naive| Utc.from_utc_datetime(&naive))
+        .map_err(|_| {
+            RaxFtpClientError::InvalidResponse(format!("Invalid FTP timestamp: '{value}'"))
+        })
+}
+
+/// Format a UTC time as an RFC 3659 time value
+pub fn format_ftp_timestamp(time: &DateTime<Utc>) -> String {
+    time.format(FTP_TIME_FORMAT).to_string()
+}
+
+/// Extract the timestamp from a "213 YYYYMMDDHHMMSS" MDTM reply
+pub fn parse_mdtm_response(response: &str) -> Result<DateTime<Utc>> {
+    let response = response.trim();
+
+    match response.strip_prefix("213") {
+        Some(rest) => parse_ftp_timestamp(rest),
+        None => Err(RaxFtpClientError::InvalidResponse(format!(
+            "Unexpected MDTM reply: '{response}'"
+        ))),
+    }
+}
+
+/// Format a remote time for display in both local time and UTC
+pub fn format_remote_time(time: &DateTime<Utc>) -> String {
+    let local: DateTime<Local> = time.with_timezone(&Local);
+    format!(
+        "{} (local)\n{} (UTC)",
+        local.format("%Y-%m-%d %H:%M:%S %Z"),
+        time.format("%Y-%m-%d %H:%M:%S UTC")
+    )
+}
+
+/// Set the modification time of a local file
+pub fn set_local_mtime(local_path: &Path, time: &DateTime<Utc>) -> Result<()> {
+    let file = File::options().write(true).open(local_path)?;
+    file.set_modified(SystemTime::from(*time))?;
+
+    info!(
+        "Set modification time of '{}' to {}",
+        local_path.display(),
+        format_ftp_timestamp(time)
+    );
+    Ok(())
+}
+
+/// Read the modification time of a local file
+pub fn get_local_mtime(local_path: &Path) -> Result<DateTime<Utc>> {
+    let modified = fs::metadata(local_path)?.modified()?;
+    let time: DateTime<Utc> = modified.into();
+
+    debug!(
+        "Local modification time of '{}' is {}",
+        local_path.display(),
+        format_ftp_timestamp(&time)
+    );
+    Ok(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ftp_timestamp() {
+        let time = parse_ftp_timestamp("20240131235959").unwrap();
+        assert_eq!(format_ftp_timestamp(&time), "20240131235959");
+
+        let time = parse_ftp_timestamp("20240131235959.123").unwrap();
+        assert_eq!(format_ftp_timestamp(&time), "20240131235959");
+
+        assert!(parse_ftp_timestamp("2024-01-31").is_err());
+    }
+
+    #[test]
+    fn test_parse_mdtm_response() {
+        let time = parse_mdtm_response("213 20231105083000\r\n").unwrap();
+        assert_eq!(format_ftp_timestamp(&time), "20231105083000");
+
+        assert!(parse_mdtm_response("550 File not found").is_err());
+    }
+}
@@ -1,24 +1,48 @@
 //! Transfer progress tracking
 
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
+/// Time window used for the moving-average transfer speed
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+
+/// Minimum time between two progress display updates
+const DISPLAY_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Summary of a finished transfer
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferSummary {
+    pub file: String,
+    pub bytes: u64,
+    pub duration_secs: f64,
+    pub speed_bps: f64,
+}
+
 /// Progress tracker for file transfers
 pub struct TransferProgress {
     total_bytes: u64,
     transferred_bytes: u64,
     start_time: Instant,
     last_update: Instant,
+    last_display: Option<Instant>,
+    samples: VecDeque<(Instant, u64)>,
 }
 
 impl TransferProgress {
-    /// Create a new progress tracker
+    /// Create a new progress tracker (use 0 when the total size is unknown)
     pub fn new(total_bytes: u64) -> Self {
         let now = Instant::now();
+        let mut samples = VecDeque::new();
+        samples.push_back((now, 0));
+
         Self {
             total_bytes,
             transferred_bytes: 0,
             start_time: now,
             last_update: now,
+            last_display: None,
+            samples,
         }
     }
 
@@ -26,6 +50,20 @@ impl TransferProgress {
     pub fn add_bytes(&mut self, bytes: u64) {
         self.transferred_bytes += bytes;
         self.last_update = Instant::now();
+        self.samples
+            .push_back((self.last_update, self.transferred_bytes));
+
+        // Drop samples that fell out of the speed window, keeping one as the baseline
+        while self.samples.len() > 2
+            && self.last_update.duration_since(self.samples[1].0) >= SPEED_WINDOW
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Check whether the total size of the transfer is known
+    pub fn has_known_total(&self) -> bool {
+        self.total_bytes > 0
     }
 
     /// Get current progress percentage
@@ -37,7 +75,7 @@ impl TransferProgress {
         }
     }
 
-    /// Get transfer speed in bytes per second
+    /// Get the average transfer speed since the start in bytes per second
     pub fn speed_bps(&self) -> f64 {
         let elapsed = self.start_time.elapsed().as_secs_f64();
         if elapsed > 0.0 {
@@ -47,6 +85,53 @@ impl TransferProgress {
         }
     }
 
+    /// Get the transfer speed over the last few seconds in bytes per second
+    pub fn moving_speed_bps(&self) -> f64 {
+        let (Some(&(oldest_time, oldest_bytes)), Some(&(newest_time, newest_bytes))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return 0.0;
+        };
+
+        let elapsed = newest_time.duration_since(oldest_time).as_secs_f64();
+        if elapsed > 0.0 {
+            (newest_bytes - oldest_bytes) as f64 / elapsed
+        } else {
+            self.speed_bps()
+        }
+    }
+
+    /// Estimate the remaining transfer time (None when size or speed is unknown)
+    pub fn eta(&self) -> Option<Duration> {
+        if !self.has_known_total() {
+            return None;
+        }
+
+        if self.is_complete() {
+            return Some(Duration::ZERO);
+        }
+
+        let remaining = self.total_bytes - self.transferred_bytes;
+        let speed = self.moving_speed_bps();
+        if speed > 0.0 {
+            Some(Duration::from_secs_f64(remaining as f64 / speed))
+        } else {
+            None
+        }
+    }
+
+    /// Check whether enough time has passed to refresh the progress display
+    pub fn should_display(&mut self) -> bool {
+        let now = Instant::now();
+        match self.last_display {
+            Some(last) if now.duration_since(last) < DISPLAY_INTERVAL => false,
+            _ => {
+                self.last_display = Some(now);
+                true
+            }
+        }
+    }
+
     /// Get total bytes
     pub fn total_bytes(&self) -> u64 {
         self.total_bytes
@@ -66,4 +151,47 @@ impl TransferProgress {
     pub fn elapsed(&self) -> Duration {
         self.start_time.elapsed()
     }
+
+    /// Summarize the transfer for reporting
+    pub fn summary(&self, file: &str) -> TransferSummary {
+        TransferSummary {
+            file: file.to_string(),
+            bytes: self.transferred_bytes,
+            duration_secs: self.elapsed().as_secs_f64(),
+            speed_bps: self.speed_bps(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_total_has_no_eta() {
+        let mut progress = TransferProgress::new(0);
+        progress.add_bytes(1024);
+
+        assert!(!progress.has_known_total());
+        assert_eq!(progress.percentage(), 0.0);
+        assert_eq!(progress.eta(), None);
+    }
+
+    #[test]
+    fn test_known_total_percentage_and_eta() {
+        let mut progress = TransferProgress::new(2048);
+        progress.add_bytes(1024);
+        assert_eq!(progress.percentage(), 50.0);
+
+        progress.add_bytes(1024);
+        assert!(progress.is_complete());
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_should_display_is_throttled() {
+        let mut progress = TransferProgress::new(100);
+        assert!(progress.should_display());
+        assert!(!progress.should_display());
+    }
 }
@@ -2,22 +2,25 @@
 
 use log::{debug, error, info};
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
 use crate::connection::data::DataConnection;
 use crate::error::{RaxFtpClientError, Result};
-use crate::terminal::progress::{display_progress, finish_progress, format_bytes};
-use crate::transfer::progress::TransferProgress;
+use crate::terminal::progress::{ProgressBar, format_bytes, report_status};
+use crate::transfer::progress::{TransferProgress, TransferSummary};
 
 /// Upload a file through the data connection with progress tracking
+///
+/// A non-zero `offset` (after REST) skips the bytes the server already has.
 pub fn upload_file_with_progress(
     data_connection: &mut DataConnection,
     local_path: &Path,
     filename: &str,
-) -> Result<()> {
+    offset: u64,
+) -> Result<TransferSummary> {
     // Open the file
-    let file = File::open(local_path).map_err(|e| RaxFtpClientError::FileNotFound {
+    let mut file = File::open(local_path).map_err(|e| RaxFtpClientError::FileNotFound {
         code: 550,
         message: format!("Cannot open local file '{}': {}", local_path.display(), e),
     })?;
@@ -29,13 +32,28 @@ pub fn upload_file_with_progress(
             code: 550,
             message: format!("Cannot get file metadata: {e}"),
         })?
-        .len();
+        .len()
+        .saturating_sub(offset);
+
+    if offset > 0 {
+        info!("Resuming '{filename}' at byte {offset}");
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| RaxFtpClientError::TransferFailed {
+                code: 550,
+                message: format!("Cannot seek to byte {offset}: {e}"),
+            })?;
+    }
 
     info!("Starting upload of '{filename}' ({file_size} bytes)");
-    println!("Uploading '{}' ({})...", filename, format_bytes(file_size));
+    report_status(&format!(
+        "Uploading '{}' ({})...",
+        filename,
+        format_bytes(file_size)
+    ));
 
     // Create progress tracker
     let mut progress = TransferProgress::new(file_size);
+    let progress_bar = ProgressBar::new(filename);
 
     // Create buffered reader
     let mut reader = BufReader::new(file);
@@ -57,28 +75,23 @@ pub fn upload_file_with_progress(
                         total_sent += bytes_sent as u64;
                         progress.add_bytes(bytes_sent as u64);
 
-                        // Update progress display every 64KB or at end
-                        if total_sent % 65536 == 0 || progress.is_complete() {
-                            display_progress(
-                                filename,
-                                progress.percentage(),
-                                progress.transferred_bytes(),
-                                progress.speed_bps(),
-                            );
+                        // Refresh the progress display at a fixed time interval
+                        if progress.should_display() {
+                            progress_bar.update(&progress);
                         }
 
                         debug!("Sent {bytes_sent} bytes, total: {total_sent}");
                     }
                     Err(e) => {
                         error!("Failed to send data: {e}");
-                        println!("\nUpload failed: {e}");
+                        report_status(&format!("Upload failed: {e}"));
                         return Err(e);
                     }
                 }
             }
             Err(e) => {
                 error!("Failed to read from file: {e}");
-                println!("\nUpload failed: Failed to read file");
+                report_status("Upload failed: Failed to read file");
                 return Err(RaxFtpClientError::TransferFailed {
                     code: 550,
                     message: format!("Failed to read file: {e}"),
@@ -88,20 +101,14 @@ pub fn upload_file_with_progress(
     }
 
     // Ensure final progress display
-    display_progress(
-        filename,
-        progress.percentage(),
-        progress.transferred_bytes(),
-        progress.speed_bps(),
-    );
-    finish_progress(); // Move to next line after progress bar
+    progress_bar.finish(&progress);
 
     info!(
         "Upload completed: {} bytes in {:?}",
         total_sent,
         progress.elapsed()
     );
-    Ok(())
+    Ok(progress.summary(filename))
 }
 
 /// Validate that a file can be uploaded